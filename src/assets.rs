@@ -0,0 +1,73 @@
+//! Module containing asset fingerprinting, used to give assets content-hashed filenames.
+
+use std::path::{Path, PathBuf};
+
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::builder::{SiteBuilder, WEBDOG_DEFAULT_PATH};
+
+/// Filename for the asset manifest, stored in the webdog path.
+pub const ASSET_MANIFEST_FILENAME: &str = "asset-manifest.json";
+/// The number of characters of an asset's hash included in its filename.
+const HASH_LENGTH: usize = 10;
+
+/// Config for fingerprinting the site's assets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FingerprintConfig {
+	/// Glob patterns of files in the root directory to fingerprint, relative to it.
+	#[serde(default)]
+	pub root: Vec<String>,
+}
+
+impl FingerprintConfig {
+	/// Builds the glob set matching the root files to fingerprint.
+	pub(crate) fn root_globs(&self) -> eyre::Result<GlobSet> {
+		let mut builder = GlobSetBuilder::new();
+		for pattern in &self.root {
+			builder.add(Glob::new(pattern)?);
+		}
+		Ok(builder.build()?)
+	}
+}
+
+/// Gets the fingerprinted version of an asset's path, with the hash inserted before its extension.
+///
+/// i.e. `styles/index.css` becomes `styles/index.0123456789.css`
+pub(crate) fn fingerprinted_path(path: &Path, hash: &str) -> PathBuf {
+	let hash = &hash[..HASH_LENGTH.min(hash.len())];
+	let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+	let filename = match path.extension() {
+		Some(extension) => format!("{stem}.{hash}.{}", extension.to_string_lossy()),
+		None => format!("{stem}.{hash}"),
+	};
+	path.with_file_name(filename)
+}
+
+/// Writes the manifest mapping each fingerprinted asset's original URL to its fingerprinted one, if fingerprinting is enabled.
+pub(crate) fn build_manifest(builder: &SiteBuilder) -> eyre::Result<()> {
+	if !builder.fingerprinting() {
+		return Ok(());
+	}
+	let manifest = builder
+		.assets
+		.lock()
+		.expect("asset manifest lock poisoned")
+		.clone();
+	let out_path = builder
+		.build_path
+		.join(
+			builder
+				.site
+				.config
+				.webdog_path
+				.as_deref()
+				.unwrap_or(WEBDOG_DEFAULT_PATH),
+		)
+		.join(ASSET_MANIFEST_FILENAME);
+	builder.write_output(
+		&out_path,
+		builder.cache_key().add_serialized(&manifest)?,
+		|| Ok(serde_json::to_string_pretty(&manifest)?),
+	)
+}
@@ -1,19 +1,35 @@
 //! Module containing the site builder.
 
-use std::{collections::HashMap, path::PathBuf};
+use std::{
+	collections::{BTreeMap, BTreeSet, HashMap},
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
 
 use eyre::{Context, OptionExt, eyre};
 use lol_html::{HtmlRewriter, Settings, element, html_content::ContentType};
 use rayon::prelude::*;
 use serde::Serialize;
-use syntect::{highlighting::ThemeSet, parsing::SyntaxSet};
+use syntect::{
+	highlighting::ThemeSet,
+	parsing::{SyntaxSet, SyntaxSetBuilder},
+};
 use tera::Tera;
 use url::Url;
 
-use crate::{PageMetadata, ROOT_PATH, SASS_PATH, Site, resource::ResourceBuilder, util};
+use crate::{
+	AlternateLink, PageMetadata, ROOT_PATH, SASS_PATH, SYNTAXES_PATH, Site, SiteConfig,
+	TEMPLATES_PATH, THEMES_PATH, TocEntry,
+	cache::{BuildCache, CACHE_FILENAME, CacheKey},
+	dependencies::{self, DependencyGraph, Dependent},
+	i18n::LanguageTemplateData,
+	images::ProcessedImage,
+	resource::ResourceBuilder,
+	util,
+};
 
 /// Default path for static webdog resources included with the site build.
-const WEBDOG_DEFAULT_PATH: &str = "webdog";
+pub(crate) const WEBDOG_DEFAULT_PATH: &str = "webdog";
 
 /// Struct containing data to be sent to templates when rendering them.
 #[derive(Debug, Serialize)]
@@ -26,6 +42,10 @@ struct TemplateData<'a, T> {
 	pub data: T,
 	/// Userdata supplied from the page.
 	pub userdata: serde_yaml_ng::Value,
+	/// The page's table of contents.
+	pub toc: &'a [TocEntry],
+	/// The page's language, if the site is multilingual.
+	pub lang: Option<LanguageTemplateData<'a>>,
 }
 
 /// Struct used to build the site.
@@ -34,6 +54,8 @@ pub struct SiteBuilder {
 	pub(crate) tera: Tera,
 	/// The syntax set used to render source code.
 	pub(crate) syntax_set: SyntaxSet,
+	/// The site's custom syntax definitions, which take priority over the default syntax set.
+	pub(crate) custom_syntax_set: SyntaxSet,
 	/// The theme set used to render source code.
 	pub(crate) theme_set: ThemeSet,
 	/// The site info used to build the site.
@@ -42,6 +64,24 @@ pub struct SiteBuilder {
 	pub build_path: PathBuf,
 	/// Whether the site is going to be served locally with the dev server.
 	pub serving: bool,
+	/// The build cache used to skip unchanged outputs.
+	pub(crate) cache: BuildCache,
+	/// Hash of the site's config, used as the base for all cache keys.
+	cache_base: String,
+	/// Hash of the site's config along with its custom syntaxes and themes, used as the base for cache keys of rendered Markdown.
+	markdown_base: String,
+	/// Hashes of each template's source, by template name.
+	template_hashes: HashMap<String, String>,
+	/// Graph of the templates each output depends on.
+	pub(crate) dependencies: Mutex<DependencyGraph>,
+	/// Hash of every loaded resource, used for pages whose output depends on resources.
+	resources_digest: String,
+	/// The site's processed images, by the URL path they're served at.
+	pub(crate) images: BTreeMap<String, ProcessedImage>,
+	/// The dimensions of the site's local images, by the URL they're served at.
+	pub(crate) image_dimensions: BTreeMap<String, (u32, u32)>,
+	/// Map of fingerprinted assets' URLs to their fingerprinted URLs.
+	pub(crate) assets: Mutex<BTreeMap<String, String>>,
 
 	/// The resource builders available to the builder.
 	pub resource_builders: HashMap<String, ResourceBuilder>,
@@ -66,31 +106,55 @@ impl SiteBuilder {
 		)?;
 		tera.autoescape_on(vec![".tera"]);
 
+		let (custom_syntax_set, theme_set) = Self::load_highlighting(&site.site_path)?;
+
 		Ok(Self {
 			tera,
 			syntax_set: SyntaxSet::load_defaults_newlines(),
-			theme_set: ThemeSet::load_defaults(),
+			custom_syntax_set,
+			theme_set,
 			resource_builders: HashMap::new(),
 			site,
 			build_path,
 			serving,
+			cache: BuildCache::disabled(),
+			cache_base: String::new(),
+			markdown_base: String::new(),
+			template_hashes: HashMap::new(),
+			dependencies: Default::default(),
+			resources_digest: String::new(),
+			images: BTreeMap::new(),
+			image_dimensions: BTreeMap::new(),
+			assets: Default::default(),
 		})
 	}
 
 	/// Prepares the site builder for use and sets up the build directory.
 	pub fn prepare(mut self) -> eyre::Result<Self> {
-		self.tera.full_reload()?;
+		self.reload_templates()?;
+
+		let cache_path = self.site.site_path.join(CACHE_FILENAME);
+		if self.serving {
+			// the dev server's output differs from normal builds, so any existing cache is invalid
+			if cache_path.exists() {
+				std::fs::remove_file(&cache_path).wrap_err("Failed to remove build cache")?;
+			}
+			self.cache = BuildCache::in_memory();
+		} else {
+			self.cache = BuildCache::load(cache_path)?;
+		}
+
 		if self.build_path.exists() {
-			for entry in self.build_path.read_dir()? {
-				let path = &entry?.path();
-				if path.is_dir() {
-					std::fs::remove_dir_all(path).with_context(|| {
-						format!("Failed to remove directory at {}", path.display())
-					})?;
-				} else {
-					std::fs::remove_file(path)
-						.with_context(|| format!("Failed to remove file at {}", path.display()))?;
-				}
+			if self.cache.is_empty() {
+				// image variants are named by what they're encoded from, so they're kept to avoid encoding every image again
+				let variants_path = self
+					.site
+					.config
+					.images
+					.as_ref()
+					.map(|images| self.build_path.join(&images.output_path));
+				util::remove_dir_contents_except(&self.build_path, variants_path.as_deref())
+					.wrap_err("Failed to remove old contents of build directory")?;
 			}
 		} else {
 			std::fs::create_dir(&self.build_path).wrap_err("Failed to create build directory")?;
@@ -103,24 +167,36 @@ impl SiteBuilder {
 				.clone()
 				.unwrap_or_else(|| WEBDOG_DEFAULT_PATH.to_string()),
 		);
-		std::fs::create_dir(&webdog_path)?;
-		std::fs::write(
-			webdog_path.join("webdog.js"),
-			include_str!("./embedded/js/webdog.js"),
+		let webdog_js = include_str!("./embedded/js/webdog.js");
+		self.write_asset(
+			&webdog_path.join("webdog.js"),
+			CacheKey::new().add(webdog_js),
+			|| Ok(webdog_js),
 		)?;
 
 		let root_path = self.site.site_path.join(ROOT_PATH);
 		if root_path.exists() {
+			let fingerprinted = match &self.site.config.fingerprint {
+				Some(fingerprint) if self.fingerprinting() => fingerprint
+					.root_globs()
+					.wrap_err("Invalid root fingerprint pattern")?,
+				_ => Default::default(),
+			};
 			for entry in walkdir::WalkDir::new(&root_path) {
 				let entry = entry?;
 				let path = entry.path();
 				if path.is_dir() {
 					continue;
 				}
-				let output_path = self.build_path.join(path.strip_prefix(&root_path)?);
-				let parent_path = output_path.parent().expect("should never fail");
-				std::fs::create_dir_all(parent_path)?;
-				std::fs::copy(path, output_path)?;
+				let relative_path = path.strip_prefix(&root_path)?;
+				let output_path = self.build_path.join(relative_path);
+				let contents = std::fs::read(path)?;
+				let key = CacheKey::new().add(&contents);
+				if fingerprinted.is_match(relative_path) {
+					// the original is kept too, since references from stylesheets and scripts aren't rewritten
+					self.write_asset(&output_path, key.clone(), || Ok(&contents))?;
+				}
+				self.write_output(&output_path, key, || Ok(contents))?;
 			}
 		}
 
@@ -135,6 +211,8 @@ impl SiteBuilder {
 			.config
 			.check(self)
 			.wrap_err("site config failed check:")?;
+		// resources render their Markdown as they're loaded, so this has to be ready first
+		self.markdown_base = self.compute_markdown_base()?;
 		self.resource_builders.clear();
 		for (prefix, config) in &self.site.config.resources {
 			self.resource_builders
@@ -145,6 +223,17 @@ impl SiteBuilder {
 			self.reload_resource_builder(&prefix)?;
 		}
 
+		self.reload_images()?;
+
+		crate::code::build_code_themes(
+			self,
+			self.site
+				.config
+				.webdog_path
+				.as_deref()
+				.unwrap_or(WEBDOG_DEFAULT_PATH),
+		)?;
+
 		Ok(())
 	}
 
@@ -157,9 +246,322 @@ impl SiteBuilder {
 		resource_builder.load_all(self)?;
 		self.resource_builders
 			.insert(builder.to_string(), resource_builder);
+		self.resources_digest = self.compute_resources_digest()?;
 		Ok(())
 	}
 
+	/// Processes the site's images and reads their dimensions again after they changed.
+	pub fn reload_images(&mut self) -> eyre::Result<()> {
+		self.image_dimensions = crate::images::read_dimensions(self)?;
+		self.images = crate::images::process_images(self)?;
+		self.cache_base = self.compute_cache_base()?;
+		Ok(())
+	}
+
+	/// Loads the site's custom syntax definitions along with the default and custom code themes.
+	fn load_highlighting(site_path: &Path) -> eyre::Result<(SyntaxSet, ThemeSet)> {
+		// custom syntaxes are kept separate from the defaults, since linking them all together again is very slow
+		let mut syntax_builder = SyntaxSetBuilder::new();
+		let syntaxes_path = site_path.join(SYNTAXES_PATH);
+		if syntaxes_path.exists() {
+			syntax_builder
+				.add_from_folder(&syntaxes_path, true)
+				.wrap_err("Failed to load syntax definitions")?;
+		}
+
+		let mut theme_set = ThemeSet::load_defaults();
+		let themes_path = site_path.join(THEMES_PATH);
+		if themes_path.exists() {
+			theme_set
+				.add_from_folder(&themes_path)
+				.wrap_err("Failed to load code themes")?;
+		}
+
+		Ok((syntax_builder.build(), theme_set))
+	}
+
+	/// Reloads the site's custom syntax definitions and code themes.
+	pub fn reload_highlighting(&mut self) -> eyre::Result<()> {
+		(self.custom_syntax_set, self.theme_set) = Self::load_highlighting(&self.site.site_path)?;
+		Ok(())
+	}
+
+	/// Reloads the site's templates.
+	pub fn reload_templates(&mut self) -> eyre::Result<()> {
+		self.tera.full_reload()?;
+
+		self.template_hashes.clear();
+		let templates_path = self.site.site_path.join(TEMPLATES_PATH);
+		if templates_path.exists() {
+			for entry in walkdir::WalkDir::new(&templates_path) {
+				let entry = entry?;
+				if entry.file_type().is_file() {
+					self.template_hashes.insert(
+						util::template_name(entry.path().strip_prefix(&templates_path)?),
+						CacheKey::new().add(std::fs::read(entry.path())?).finish(),
+					);
+				}
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Reloads a single resource after its source file changed, removing its output if the resource was removed.
+	pub fn reload_resource(&mut self, builder: &str, path: &Path) -> eyre::Result<()> {
+		let mut resource_builder = self
+			.resource_builders
+			.remove(builder)
+			.ok_or_else(|| eyre!("missing resource builder: {builder}"))?;
+		let result = resource_builder.reload_one(self, path);
+		if let Ok(Some((id, language))) = &result {
+			let out_path = resource_builder.build_path(self, id, language.as_deref());
+			if out_path.exists() {
+				std::fs::remove_file(&out_path).with_context(|| {
+					format!("Failed to remove resource at {}", out_path.display())
+				})?;
+			}
+		}
+		self.resource_builders
+			.insert(builder.to_string(), resource_builder);
+		result?;
+		self.resources_digest = self.compute_resources_digest()?;
+		Ok(())
+	}
+
+	/// Computes the hash of the site's config, which every build output depends on.
+	fn compute_cache_base(&self) -> eyre::Result<String> {
+		// processed images and image dimensions change the HTML of any page which uses them
+		Ok(CacheKey::new()
+			.add(&self.markdown_base)
+			.add_serialized(&self.images)?
+			.add_serialized(&self.image_dimensions)?
+			.finish())
+	}
+
+	/// Computes the hash of the site's config, custom syntaxes, and custom themes, which everything rendered from Markdown depends on.
+	fn compute_markdown_base(&self) -> eyre::Result<String> {
+		let mut key = CacheKey::new().add(env!("CARGO_PKG_VERSION"));
+		let config_path = self.site.site_path.join(SiteConfig::FILENAME);
+		if config_path.exists() {
+			key = key.add(std::fs::read(config_path)?);
+		}
+		// custom syntaxes and themes may change the output of any code block
+		for dir in [SYNTAXES_PATH, THEMES_PATH] {
+			let dir_path = self.site.site_path.join(dir);
+			if !dir_path.exists() {
+				continue;
+			}
+			for entry in walkdir::WalkDir::new(&dir_path).sort_by_file_name() {
+				let entry = entry?;
+				if entry.file_type().is_file() {
+					key = key
+						.add(
+							entry
+								.path()
+								.strip_prefix(&dir_path)?
+								.to_string_lossy()
+								.as_bytes(),
+						)
+						.add(std::fs::read(entry.path())?);
+				}
+			}
+		}
+		Ok(key.finish())
+	}
+
+	/// Computes the hash of every loaded resource.
+	fn compute_resources_digest(&self) -> eyre::Result<String> {
+		let mut prefixes: Vec<_> = self.resource_builders.keys().collect();
+		prefixes.sort();
+		let mut key = CacheKey::new();
+		for prefix in prefixes {
+			key = key
+				.add(prefix)
+				.add_serialized(&self.resource_builders[prefix].loaded_metadata)?;
+		}
+		Ok(key.finish())
+	}
+
+	/// Gets the hash which cache keys for rendered Markdown are seeded with.
+	pub(crate) fn markdown_base(&self) -> &str {
+		&self.markdown_base
+	}
+
+	/// Creates a new cache key for a build output, seeded with the site's config.
+	pub(crate) fn cache_key(&self) -> CacheKey {
+		CacheKey::new().add(&self.cache_base)
+	}
+
+	/// Creates a new cache key for a build output which depends on the given templates.
+	pub(crate) fn cache_key_with_templates(&self, templates: &BTreeSet<String>) -> CacheKey {
+		let mut key = self.cache_key();
+		for template in templates {
+			key = key.add(template).add(
+				self.template_hashes
+					.get(template)
+					.map_or("", |h| h.as_str()),
+			);
+		}
+		// rendered HTML links to fingerprinted assets by their hashes
+		for (url, asset_url) in self
+			.assets
+			.lock()
+			.expect("asset manifest lock poisoned")
+			.iter()
+		{
+			key = key.add(url).add(asset_url);
+		}
+		key
+	}
+
+	/// Gets every template the given templates depend on, including themselves.
+	pub(crate) fn template_dependencies<'t>(
+		&self,
+		templates: impl IntoIterator<Item = &'t str>,
+	) -> BTreeSet<String> {
+		dependencies::collect_templates(&self.tera, templates)
+	}
+
+	/// Gets every template a page depends on.
+	fn page_dependencies(
+		&self,
+		metadata: Option<&PageMetadata>,
+		content: &str,
+	) -> BTreeSet<String> {
+		let mut templates = vec![
+			metadata
+				.and_then(|m| m.template.clone())
+				.unwrap_or_else(|| "base.tera".to_string()),
+		];
+		templates.extend(dependencies::find_partials(content));
+		if let Some(extra) = metadata.and_then(|m| m.extra.as_ref())
+			&& let Some(template) = extra.inner.get("template").and_then(|t| t.as_str())
+		{
+			templates.push(template.to_string());
+		}
+		self.template_dependencies(templates.iter().map(|t| t.as_str()))
+	}
+
+	/// Rebuilds every output which depends on the given template.
+	pub fn build_template_dependents(&self, template: &str) -> eyre::Result<()> {
+		let dependents = self
+			.dependencies
+			.lock()
+			.expect("dependency graph lock poisoned")
+			.dependents(template);
+		for dependent in dependents {
+			match dependent {
+				Dependent::Page(page_name) => {
+					if self.site.page_index.contains_key(&page_name) {
+						self.build_page(&page_name)?;
+					}
+				}
+				Dependent::Resources(prefix) => {
+					if self.resource_builders.contains_key(&prefix) {
+						self.build_resources(&prefix)?;
+					}
+				}
+			}
+		}
+		Ok(())
+	}
+
+	/// Writes a build output to the given path, skipping rendering it if the build cache says it's up to date.
+	pub(crate) fn write_output<C>(
+		&self,
+		out_path: &Path,
+		key: CacheKey,
+		render: impl FnOnce() -> eyre::Result<C>,
+	) -> eyre::Result<()>
+	where
+		C: AsRef<[u8]>,
+	{
+		let relative_path = out_path.strip_prefix(&self.build_path)?;
+		let key = key.finish();
+		if self.cache.is_fresh(&self.build_path, relative_path, &key) {
+			return Ok(());
+		}
+
+		Self::write_file(out_path, render()?)?;
+		self.cache.record(relative_path, &key);
+
+		Ok(())
+	}
+
+	/// Writes a file to the given path, creating its parent directories if needed.
+	fn write_file(out_path: &Path, contents: impl AsRef<[u8]>) -> eyre::Result<()> {
+		if let Some(parent) = out_path.parent() {
+			std::fs::create_dir_all(parent)
+				.with_context(|| format!("Failed to create directory at {}", parent.display()))?;
+		}
+		std::fs::write(out_path, contents)
+			.with_context(|| format!("Failed to write output at {}", out_path.display()))
+	}
+
+	/// Whether assets should be fingerprinted. The dev server never fingerprints assets.
+	pub(crate) fn fingerprinting(&self) -> bool {
+		self.site.config.fingerprint.is_some() && !self.serving
+	}
+
+	/// Writes an asset to the given path, giving it a fingerprinted filename and recording it in the asset manifest if fingerprinting is enabled.
+	///
+	/// The fingerprint is a hash of the rendered asset, while the cache key only decides whether it needs to be rendered again.
+	pub(crate) fn write_asset<C>(
+		&self,
+		out_path: &Path,
+		key: CacheKey,
+		render: impl FnOnce() -> eyre::Result<C>,
+	) -> eyre::Result<()>
+	where
+		C: AsRef<[u8]>,
+	{
+		if !self.fingerprinting() {
+			return self.write_output(out_path, key, render);
+		}
+		let relative_path = out_path.strip_prefix(&self.build_path)?;
+		let key = key.finish();
+		let fingerprinted_path = match self
+			.cache
+			.fresh_asset(&self.build_path, relative_path, &key)
+		{
+			Some(path) => self.build_path.join(path),
+			None => {
+				let out = render()?;
+				let hash = CacheKey::new().add(out.as_ref()).finish();
+				let fingerprinted_path = crate::assets::fingerprinted_path(out_path, &hash);
+				Self::write_file(&fingerprinted_path, out)?;
+				self.cache.record_asset(
+					relative_path,
+					&key,
+					fingerprinted_path.strip_prefix(&self.build_path)?,
+				);
+				fingerprinted_path
+			}
+		};
+		let url = |path: &Path| -> eyre::Result<String> {
+			Ok(format!(
+				"/{}",
+				util::template_name(path.strip_prefix(&self.build_path)?)
+			))
+		};
+		self.assets
+			.lock()
+			.expect("asset manifest lock poisoned")
+			.insert(url(out_path)?, url(&fingerprinted_path)?);
+		Ok(())
+	}
+
+	/// Gets the URL a site asset is served at, which differs from its original URL if it was fingerprinted.
+	pub(crate) fn asset_url(&self, url: &str) -> String {
+		self.assets
+			.lock()
+			.expect("asset manifest lock poisoned")
+			.get(url)
+			.cloned()
+			.unwrap_or_else(|| url.to_string())
+	}
+
 	/// Function to rewrite HTML wow.
 	#[allow(clippy::too_many_arguments)]
 	pub fn rewrite_html(
@@ -169,6 +571,8 @@ impl SiteBuilder {
 		head: &Option<String>,
 		scripts: &[String],
 		styles: &[String],
+		alternates: &[AlternateLink],
+		language: Option<&str>,
 		is_partial: bool,
 		webdog_path: &str,
 	) -> eyre::Result<String> {
@@ -232,6 +636,12 @@ impl SiteBuilder {
 			let mut rewriter = HtmlRewriter::new(
 				Settings {
 					element_content_handlers: vec![
+						element!("html", |el| {
+							if let Some(language) = language {
+								el.set_attribute("lang", language)?;
+							}
+							Ok(())
+						}),
 						element!("body", |el| {
 							if self.serving {
 								el.set_attribute("class", "debug")?;
@@ -247,20 +657,31 @@ impl SiteBuilder {
 							for script in scripts {
 								el.append(
 									&format!(
-										r#"<script type="text/javascript" src="{script}" defer></script>"#
+										r#"<script type="text/javascript" src="{}" defer></script>"#,
+										self.asset_url(script)
 									),
 									ContentType::Html,
 								);
 							}
 							for style in styles {
 								el.append(
-									&format!(r#"<link rel="stylesheet" href="/styles/{style}">"#),
+									&format!(
+										r#"<link rel="stylesheet" href="{}">"#,
+										self.asset_url(&format!("/styles/{style}"))
+									),
 									ContentType::Html,
 								);
 							}
+							for link in crate::code::theme_links(self, webdog_path) {
+								el.append(&link, ContentType::Html);
+							}
+							for alternate in alternates {
+								el.append(&alternate.build(), ContentType::Html);
+							}
 							el.append(
 								&format!(
-									r#"<script type="text/javascript" src="/{webdog_path}/webdog.js" defer></script>"#
+									r#"<script type="text/javascript" src="{}" defer></script>"#,
+									self.asset_url(&format!("/{webdog_path}/webdog.js"))
 								),
 								ContentType::Html,
 							);
@@ -285,6 +706,27 @@ impl SiteBuilder {
 								src = new_src;
 								el.set_attribute("src", &src)?;
 							}
+							if let Some(src) = el.get_attribute("src") {
+								if let Some((width, height)) = self.image_dimensions.get(&src)
+									&& !el.has_attribute("width")
+									&& !el.has_attribute("height")
+								{
+									el.set_attribute("width", &width.to_string())?;
+									el.set_attribute("height", &height.to_string())?;
+								}
+								if let Some(image) = self.images.get(&src) {
+									self.rewrite_img(el, image)?;
+								}
+							}
+							if el.has_attribute("wd-eager") {
+								el.remove_attribute("wd-eager");
+							} else {
+								for (name, value) in [("loading", "lazy"), ("decoding", "async")] {
+									if !el.has_attribute(name) {
+										el.set_attribute(name, value)?;
+									}
+								}
+							}
 
 							Ok(())
 						}),
@@ -325,6 +767,20 @@ impl SiteBuilder {
 								}
 							}
 
+							Ok(())
+						}),
+						element!("[href], [src]", |el| {
+							if self.fingerprinting() {
+								for attr in ["href", "src"] {
+									if let Some(url) = el.get_attribute(attr) {
+										let asset_url = self.asset_url(&url);
+										if asset_url != url {
+											el.set_attribute(attr, &asset_url)?;
+										}
+									}
+								}
+							}
+
 							Ok(())
 						}),
 					],
@@ -343,6 +799,42 @@ impl SiteBuilder {
 		Ok(output)
 	}
 
+	/// Rewrites an `img` tag to use the variants of a processed image, wrapping it in a `picture` tag if there are multiple formats.
+	fn rewrite_img(
+		&self,
+		el: &mut lol_html::html_content::Element,
+		image: &ProcessedImage,
+	) -> eyre::Result<()> {
+		let Some(config) = &self.site.config.images else {
+			return Ok(());
+		};
+		let sizes = el
+			.get_attribute("sizes")
+			.unwrap_or_else(|| config.sizes.clone());
+		let Some((fallback, sources)) = image.sources.split_last() else {
+			return Ok(());
+		};
+		el.set_attribute("src", &fallback.largest)?;
+		if !el.has_attribute("srcset") {
+			el.set_attribute("srcset", &fallback.srcset)?;
+			el.set_attribute("sizes", &sizes)?;
+		}
+		if !sources.is_empty() {
+			let mut picture = String::from("<picture>");
+			for source in sources {
+				picture.push_str(&format!(
+					r#"<source type="{}" srcset="{}" sizes="{}">"#,
+					source.mime_type,
+					util::escape_attribute(&source.srcset),
+					util::escape_attribute(&sizes)
+				));
+			}
+			el.before(&picture, ContentType::Html);
+			el.after("</picture>", ContentType::Html);
+		}
+		Ok(())
+	}
+
 	/// Helper to build a page without writing it to disk.
 	pub fn build_page_raw<T>(
 		&self,
@@ -355,11 +847,47 @@ impl SiteBuilder {
 	{
 		let extra = page_metadata.extra.take();
 
+		let language = page_metadata.language.as_deref().and_then(|code| {
+			let i18n = self.site.config.i18n.as_ref()?;
+			let config = i18n.languages.get(code)?;
+			Some(LanguageTemplateData {
+				code,
+				name: &config.name,
+				prefix: format!("/{}", i18n.prefix(code)),
+				title: config.title.as_ref().unwrap_or(&self.site.config.title),
+				description: config
+					.description
+					.as_ref()
+					.unwrap_or(&self.site.config.description),
+				strings: &config.strings,
+				translations: &page_metadata.translations,
+			})
+		});
+		let site_title = language
+			.as_ref()
+			.map_or(self.site.config.title.as_str(), |l| l.title);
+
 		let title = match &page_metadata.title {
-			Some(page_title) => format!("{} / {}", self.site.config.title, page_title),
-			_ => self.site.config.title.clone(),
+			Some(page_title) => format!("{} / {}", site_title, page_title),
+			_ => site_title.to_string(),
 		};
 
+		let mut alternates = page_metadata.alternates.clone();
+		if let Some(i18n) = &self.site.config.i18n {
+			for translation in &page_metadata.translations {
+				let href = self
+					.site
+					.config
+					.base_url
+					.join(translation.url.trim_start_matches('/'))?
+					.to_string();
+				if translation.code == i18n.default_language {
+					alternates.push(AlternateLink::translation(href.clone(), "x-default"));
+				}
+				alternates.push(AlternateLink::translation(href, &translation.code));
+			}
+		}
+
 		let head = if let Some(embed) = page_metadata.embed {
 			Some(embed.build(self)?)
 		} else {
@@ -375,6 +903,8 @@ impl SiteBuilder {
 				title: &title,
 				data: extra_data,
 				userdata: page_metadata.userdata,
+				toc: &page_metadata.toc,
+				lang: language,
 			})?,
 		)?;
 
@@ -385,6 +915,8 @@ impl SiteBuilder {
 			&head,
 			&page_metadata.scripts,
 			&page_metadata.styles,
+			&alternates,
+			page_metadata.language.as_deref(),
 			page_metadata.is_partial,
 			&self
 				.site
@@ -413,22 +945,47 @@ impl SiteBuilder {
 
 		let input = std::fs::read_to_string(page_path)
 			.with_context(|| format!("Failed to read page at {}", page_path.display()))?;
-		let page = crate::frontmatter::FrontMatter::parse(input)?;
-
-		let page_html = util::render_markdown(self, &page.content)?;
-
-		let out = self.build_page_raw(page.data.unwrap_or_default(), &page_html, ())?;
+		let page = crate::frontmatter::FrontMatter::<PageMetadata>::parse(input.clone())?;
+		let templates = self.page_dependencies(page.data.as_ref(), &page.content);
+		let mut key = self
+			.cache_key_with_templates(&templates)
+			.add(page_name)
+			.add(&input);
+		self.dependencies
+			.lock()
+			.expect("dependency graph lock poisoned")
+			.set(Dependent::Page(page_name.to_string()), templates);
+		if page.data.as_ref().is_some_and(|data| data.extra.is_some()) {
+			// extras may render resource data into the page
+			key = key.add(&self.resources_digest);
+		}
+		let language = self
+			.site
+			.page_languages
+			.get(page_name)
+			.map(|l| l.language.clone());
+		let translations: Vec<_> = self
+			.site
+			.page_translations(page_name)
+			.into_iter()
+			.map(|(_, translation)| translation)
+			.collect();
+		key = key.add_serialized(&translations)?;
 
 		let out_path = self.build_path.join(page_name).with_extension("html");
-		std::fs::create_dir_all(out_path.parent().unwrap())
-			.with_context(|| format!("Failed to create directory for page {}", page_name))?;
-		std::fs::write(&out_path, out).with_context(|| {
-			format!(
-				"Failed to create HTML file at {} for page {}",
-				out_path.display(),
-				page_name
-			)
-		})?;
+		self.write_output(&out_path, key, || {
+			let rendered = util::render_markdown(
+				self,
+				&page.content,
+				page.data.as_ref().and_then(|data| data.markdown.as_ref()),
+			)?;
+			let mut metadata = page.data.unwrap_or_default();
+			metadata.toc = rendered.toc;
+			metadata.language = language;
+			metadata.translations = translations;
+			self.build_page_raw(metadata, &rendered.html, ())
+		})
+		.with_context(|| format!("Failed to build page {}", page_name))?;
 
 		Ok(())
 	}
@@ -444,33 +1001,70 @@ impl SiteBuilder {
 				.wrap_err("Failed to remove old contents of styles directory")?;
 		}
 		let sass_path = self.site.site_path.join(SASS_PATH);
-		for sheet in &self.site.config.sass_styles {
-			let sheet_path = sass_path.join(sheet);
-			if let Some(sheet_path) = sheet_path.to_str() {
-				match grass::from_path(sheet_path, &grass::Options::default()) {
-					Ok(mut css) => {
-						if !self.serving {
-							css = minifier::css::minify(&css)
-								.map_err(|err| eyre::anyhow!(err))?
-								.to_string();
-						}
-						std::fs::write(styles_path.join(sheet).with_extension("css"), css)
-							.with_context(|| {
-								format!("Failed to write new CSS file for Sass: {:?}", sheet)
-							})?;
-					}
-					Err(e) => eprintln!(
-						"Failed to compile Sass stylesheet at {:?}: {}",
-						sheet_path, e
-					),
+		let sass_config = &self.site.config.sass;
+		// imports can't be tracked easily, so every stylesheet depends on every Sass file
+		let mut sass_key = self.cache_key();
+		for dir in std::iter::once(sass_path.clone()).chain(
+			sass_config
+				.load_paths
+				.iter()
+				.map(|path| self.site.site_path.join(path)),
+		) {
+			if !dir.exists() {
+				continue;
+			}
+			for entry in walkdir::WalkDir::new(&dir).sort_by_file_name() {
+				let entry = entry?;
+				if entry.file_type().is_file() {
+					sass_key = sass_key
+						.add(
+							entry
+								.path()
+								.strip_prefix(&self.site.site_path)
+								.unwrap_or(entry.path())
+								.to_string_lossy()
+								.as_bytes(),
+						)
+						.add(std::fs::read(entry.path())?);
 				}
-			} else {
-				eprintln!(
-					"Sass stylesheet path contains invalid UTF-8: {:?}",
-					sheet_path
-				);
 			}
 		}
+		let options = sass_config.options(&self.site.site_path);
+		for sheet in &self.site.config.sass_styles {
+			let sheet_path = sass_path.join(sheet.path());
+			self.write_asset(
+				&styles_path.join(sheet.output_path()),
+				sass_key
+					.clone()
+					.add(sheet_path.to_string_lossy().as_bytes()),
+				|| {
+					let mut css =
+						grass::from_path(&sheet_path, &options).map_err(|e| eyre!("{e}"))?;
+					if sass_config.style.is_none() && !self.serving {
+						css = minifier::css::minify(&css)
+							.map_err(|err| eyre::anyhow!(err))?
+							.to_string();
+					}
+					if self.serving {
+						// grass can't produce source maps, so served stylesheets name their source instead
+						css = format!(
+							"/* built from {} */\n{css}",
+							sheet_path
+								.strip_prefix(&self.site.site_path)
+								.unwrap_or(&sheet_path)
+								.display()
+						);
+					}
+					Ok(css)
+				},
+			)
+			.with_context(|| {
+				format!(
+					"Failed to compile Sass stylesheet at {}",
+					sheet_path.display()
+				)
+			})?;
+		}
 
 		Ok(())
 	}
@@ -487,31 +1081,49 @@ impl SiteBuilder {
 
 	/// Builds all resource types.
 	pub fn build_all_resources(&self) -> eyre::Result<()> {
-		for builder in self.resource_builders.values() {
-			builder.build_all(self)?;
+		for prefix in self.resource_builders.keys() {
+			self.build_resources(prefix)?;
 		}
 		Ok(())
 	}
 
 	/// Builds a resource type from the site.
 	pub fn build_resources(&self, resource: &str) -> eyre::Result<()> {
-		self.resource_builders
+		let resource_builder = self
+			.resource_builders
 			.get(resource)
-			.ok_or_else(|| eyre!("missing resource: {resource}"))?
-			.build_all(self)
+			.ok_or_else(|| eyre!("missing resource: {resource}"))?;
+		self.dependencies
+			.lock()
+			.expect("dependency graph lock poisoned")
+			.set(
+				Dependent::Resources(resource.to_string()),
+				resource_builder.template_dependencies(self),
+			);
+		resource_builder.build_all(self)
 	}
 
 	/// Builds the entire site.
 	pub fn build_all(&self) -> eyre::Result<()> {
-		self.build_all_pages()?;
-		self.build_sass()?;
-
-		for (_source_path, config) in self.site.config.resources.iter() {
-			let mut res_builder = ResourceBuilder::new(config.clone());
-			res_builder.load_all(self)?;
-			res_builder.build_all(self)?;
+		let result = (|| {
+			// assets are built first so pages can link to their fingerprinted names
+			self.build_sass()?;
+			crate::search::build_search_index(self)?;
+			self.build_all_pages()?;
+			self.build_all_resources()?;
+			crate::sitemap::build_sitemap(self)?;
+			crate::assets::build_manifest(self)
+		})();
+		if result.is_err() {
+			// outputs written before the failure need to be rebuilt once their inputs change back
+			if let Err(e) = self.cache.save_failed() {
+				eprintln!("Failed to save build cache: {e}");
+			}
+			return result;
 		}
 
+		self.cache.finish(&self.build_path)?;
+
 		Ok(())
 	}
 }
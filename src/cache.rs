@@ -0,0 +1,413 @@
+//! Module containing the persistent build cache used for incremental builds.
+
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::util::RenderedMarkdown;
+
+/// The filename for the build cache, stored in the site's directory.
+pub const CACHE_FILENAME: &str = ".webdog-cache.yaml";
+
+/// Helper for building content hashes used as cache keys.
+#[derive(Debug, Clone, Default)]
+pub struct CacheKey(Sha256);
+
+impl CacheKey {
+	/// Creates a new, empty cache key.
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Adds some input to the cache key.
+	pub fn add(mut self, input: impl AsRef<[u8]>) -> Self {
+		let input = input.as_ref();
+		// prefix each input with its length so that inputs can't bleed into each other
+		self.0.update((input.len() as u64).to_le_bytes());
+		self.0.update(input);
+		self
+	}
+
+	/// Adds some serializable input to the cache key.
+	pub fn add_serialized(self, input: &impl Serialize) -> eyre::Result<Self> {
+		Ok(self.add(serde_yaml_ng::to_string(input)?))
+	}
+
+	/// Finishes the cache key, returning its hex representation.
+	pub fn finish(self) -> String {
+		format!("{:x}", self.0.finalize())
+	}
+}
+
+/// The data stored in the build cache.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CacheData {
+	/// The version of webdog which wrote the cache.
+	version: String,
+	/// Map of build outputs, relative to the build directory, to the hash of the inputs which produced them.
+	outputs: BTreeMap<PathBuf, String>,
+	/// Map of fingerprinted assets' original paths, relative to the build directory, to where they were written.
+	#[serde(default)]
+	assets: BTreeMap<PathBuf, CachedAsset>,
+	/// Map of hashes of Markdown and everything it's rendered with to the rendered Markdown.
+	#[serde(default)]
+	markdown: BTreeMap<String, RenderedMarkdown>,
+}
+
+/// A fingerprinted asset written by a previous build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedAsset {
+	/// The hash of the inputs which produced the asset.
+	key: String,
+	/// The asset's fingerprinted path, relative to the build directory.
+	path: PathBuf,
+}
+
+/// Struct containing the build cache.
+#[derive(Debug)]
+pub struct BuildCache {
+	/// Whether the cache is enabled. If not, every output is rebuilt.
+	enabled: bool,
+	/// The path the cache is saved to. If `None`, the cache is only kept in memory.
+	path: Option<PathBuf>,
+	/// The cache data from the previous build.
+	previous: CacheData,
+	/// The cache data for the current build.
+	current: Mutex<CacheData>,
+}
+
+impl BuildCache {
+	/// Creates a disabled build cache.
+	pub fn disabled() -> Self {
+		Self {
+			enabled: false,
+			path: None,
+			previous: Default::default(),
+			current: Default::default(),
+		}
+	}
+
+	/// Creates a build cache which is only kept in memory, used by the dev server to skip rewriting unchanged outputs.
+	pub fn in_memory() -> Self {
+		Self {
+			enabled: true,
+			path: None,
+			previous: Default::default(),
+			current: Default::default(),
+		}
+	}
+
+	/// Loads the build cache from the given path, returning an empty cache if it's missing or from a different version of webdog.
+	///
+	/// The cache file is removed once loaded and only written again when the build ends, so a build which is interrupted before then can't leave the cache describing outputs it already replaced.
+	pub fn load(path: PathBuf) -> eyre::Result<Self> {
+		let previous = if path.exists() {
+			let data: CacheData = serde_yaml_ng::from_str(
+				&std::fs::read_to_string(&path).wrap_err("Failed to read build cache")?,
+			)
+			.unwrap_or_default();
+			std::fs::remove_file(&path).wrap_err("Failed to remove build cache")?;
+			if data.version == env!("CARGO_PKG_VERSION") {
+				data
+			} else {
+				Default::default()
+			}
+		} else {
+			Default::default()
+		};
+
+		Ok(Self {
+			enabled: true,
+			path: Some(path),
+			previous,
+			current: Mutex::new(CacheData {
+				version: env!("CARGO_PKG_VERSION").to_string(),
+				outputs: Default::default(),
+				assets: Default::default(),
+				markdown: Default::default(),
+			}),
+		})
+	}
+
+	/// Whether the cache is enabled.
+	pub fn enabled(&self) -> bool {
+		self.enabled
+	}
+
+	/// Whether the cache has any outputs from a previous build.
+	pub fn is_empty(&self) -> bool {
+		self.previous.outputs.is_empty()
+	}
+
+	/// Checks whether the given output is up to date, marking it as part of the current build if so.
+	pub fn is_fresh(&self, build_path: &Path, output: &Path, key: &str) -> bool {
+		if !self.enabled() {
+			return false;
+		}
+		let mut current = self.current.lock().expect("build cache lock poisoned");
+		let fresh = current
+			.outputs
+			.get(output)
+			.or_else(|| self.previous.outputs.get(output))
+			.map(|k| k.as_str())
+			== Some(key)
+			&& build_path.join(output).exists();
+		if fresh {
+			current.outputs.insert(output.to_owned(), key.to_owned());
+		}
+		fresh
+	}
+
+	/// Records an output as being part of the current build.
+	pub fn record(&self, output: &Path, key: &str) {
+		if !self.enabled() {
+			return;
+		}
+		self.current
+			.lock()
+			.expect("build cache lock poisoned")
+			.outputs
+			.insert(output.to_owned(), key.to_owned());
+	}
+
+	/// Checks whether the given fingerprinted asset is up to date, returning its fingerprinted path and marking it as part of the current build if so.
+	pub fn fresh_asset(&self, build_path: &Path, output: &Path, key: &str) -> Option<PathBuf> {
+		if !self.enabled() {
+			return None;
+		}
+		let mut current = self.current.lock().expect("build cache lock poisoned");
+		let asset = current
+			.assets
+			.get(output)
+			.or_else(|| self.previous.assets.get(output))
+			.filter(|asset| asset.key == key && build_path.join(&asset.path).exists())
+			.cloned()?;
+		current
+			.outputs
+			.insert(asset.path.clone(), asset.key.clone());
+		current.assets.insert(output.to_owned(), asset.clone());
+		Some(asset.path)
+	}
+
+	/// Records a fingerprinted asset as being part of the current build.
+	pub fn record_asset(&self, output: &Path, key: &str, path: &Path) {
+		if !self.enabled() {
+			return;
+		}
+		let mut current = self.current.lock().expect("build cache lock poisoned");
+		current.outputs.insert(path.to_owned(), key.to_owned());
+		current.assets.insert(
+			output.to_owned(),
+			CachedAsset {
+				key: key.to_owned(),
+				path: path.to_owned(),
+			},
+		);
+	}
+
+	/// Gets some Markdown rendered by a previous build by its cache key, marking it as part of the current build.
+	pub fn rendered_markdown(&self, key: &str) -> Option<RenderedMarkdown> {
+		if !self.enabled() {
+			return None;
+		}
+		let mut current = self.current.lock().expect("build cache lock poisoned");
+		let rendered = current
+			.markdown
+			.get(key)
+			.or_else(|| self.previous.markdown.get(key))
+			.cloned()?;
+		current.markdown.insert(key.to_owned(), rendered.clone());
+		Some(rendered)
+	}
+
+	/// Records some rendered Markdown as being part of the current build.
+	pub fn record_markdown(&self, key: &str, rendered: &RenderedMarkdown) {
+		if !self.enabled() {
+			return;
+		}
+		self.current
+			.lock()
+			.expect("build cache lock poisoned")
+			.markdown
+			.insert(key.to_owned(), rendered.clone());
+	}
+
+	/// Finishes the build, removing any stale outputs from the previous build and saving the cache.
+	pub fn finish(&self, build_path: &Path) -> eyre::Result<()> {
+		let Some(path) = &self.path else {
+			return Ok(());
+		};
+		let current = self.current.lock().expect("build cache lock poisoned");
+
+		for output in self.previous.outputs.keys() {
+			if current.outputs.contains_key(output) {
+				continue;
+			}
+			let output_path = build_path.join(output);
+			if output_path.is_file() {
+				std::fs::remove_file(&output_path).with_context(|| {
+					format!("Failed to remove stale output at {}", output_path.display())
+				})?;
+				// clean up any directories left empty by the removal
+				let mut parent = output_path.parent();
+				while let Some(dir) = parent
+					&& dir != build_path
+					&& dir.read_dir()?.next().is_none()
+				{
+					std::fs::remove_dir(dir)?;
+					parent = dir.parent();
+				}
+			}
+		}
+
+		std::fs::write(path, serde_yaml_ng::to_string(&*current)?)
+			.wrap_err("Failed to write build cache")?;
+
+		Ok(())
+	}
+
+	/// Saves the cache after a failed build, keeping the previous build's outputs along with those the failed build recorded.
+	///
+	/// Outputs the failed build never reached are still fresh, and those it rewrote are recorded with their new inputs. Nothing is removed from the build directory.
+	pub fn save_failed(&self) -> eyre::Result<()> {
+		let Some(path) = &self.path else {
+			return Ok(());
+		};
+		let current = self.current.lock().expect("build cache lock poisoned");
+		let mut data = CacheData {
+			version: current.version.clone(),
+			outputs: self.previous.outputs.clone(),
+			assets: self.previous.assets.clone(),
+			markdown: self.previous.markdown.clone(),
+		};
+		data.outputs.extend(current.outputs.clone());
+		data.assets.extend(current.assets.clone());
+		data.markdown.extend(current.markdown.clone());
+
+		std::fs::write(path, serde_yaml_ng::to_string(&data)?)
+			.wrap_err("Failed to write build cache")?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::path::{Path, PathBuf};
+
+	use super::{BuildCache, CACHE_FILENAME};
+
+	/// Creates an empty directory for a test to build into.
+	fn test_dir(name: &str) -> PathBuf {
+		let dir = std::env::temp_dir().join(format!("webdog-cache-{}-{name}", std::process::id()));
+		if dir.exists() {
+			std::fs::remove_dir_all(&dir).unwrap();
+		}
+		std::fs::create_dir_all(dir.join("build")).unwrap();
+		dir
+	}
+
+	/// Writes an output and records it in the cache, like the site builder does.
+	fn write(cache: &BuildCache, build_path: &Path, output: &str, key: &str) {
+		std::fs::write(build_path.join(output), key).unwrap();
+		cache.record(Path::new(output), key);
+	}
+
+	#[test]
+	fn outputs_are_fresh_until_their_inputs_change() {
+		let dir = test_dir("fresh");
+		let build_path = dir.join("build");
+		let cache_path = dir.join(CACHE_FILENAME);
+
+		let cache = BuildCache::load(cache_path.clone()).unwrap();
+		assert!(cache.is_empty());
+		assert!(!cache.is_fresh(&build_path, Path::new("index.html"), "a"));
+		write(&cache, &build_path, "index.html", "a");
+		cache.finish(&build_path).unwrap();
+
+		let cache = BuildCache::load(cache_path.clone()).unwrap();
+		assert!(!cache.is_empty());
+		assert!(cache.is_fresh(&build_path, Path::new("index.html"), "a"));
+		assert!(!cache.is_fresh(&build_path, Path::new("index.html"), "b"));
+		assert!(!cache.is_fresh(&build_path, Path::new("other.html"), "a"));
+
+		// outputs removed from the build directory are never fresh
+		std::fs::remove_file(build_path.join("index.html")).unwrap();
+		assert!(!cache.is_fresh(&build_path, Path::new("index.html"), "a"));
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn finish_removes_stale_outputs() {
+		let dir = test_dir("stale");
+		let build_path = dir.join("build");
+		let cache_path = dir.join(CACHE_FILENAME);
+		std::fs::create_dir(build_path.join("old")).unwrap();
+
+		let cache = BuildCache::load(cache_path.clone()).unwrap();
+		write(&cache, &build_path, "index.html", "a");
+		write(&cache, &build_path, "old/page.html", "a");
+		cache.finish(&build_path).unwrap();
+
+		let cache = BuildCache::load(cache_path).unwrap();
+		assert!(cache.is_fresh(&build_path, Path::new("index.html"), "a"));
+		cache.finish(&build_path).unwrap();
+		assert!(build_path.join("index.html").exists());
+		assert!(!build_path.join("old").exists());
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn failed_builds_keep_rewritten_outputs_stale() {
+		let dir = test_dir("failed");
+		let build_path = dir.join("build");
+		let cache_path = dir.join(CACHE_FILENAME);
+
+		let cache = BuildCache::load(cache_path.clone()).unwrap();
+		write(&cache, &build_path, "index.html", "a");
+		write(&cache, &build_path, "blog.html", "a");
+		cache.finish(&build_path).unwrap();
+
+		// the page changes and is rewritten, but the build fails before it gets to the rest
+		let cache = BuildCache::load(cache_path.clone()).unwrap();
+		assert!(!cache.is_fresh(&build_path, Path::new("index.html"), "b"));
+		write(&cache, &build_path, "index.html", "b");
+		cache.save_failed().unwrap();
+
+		// changing the page back has to rebuild it, while outputs the build never reached are untouched
+		let cache = BuildCache::load(cache_path).unwrap();
+		assert!(!cache.is_fresh(&build_path, Path::new("index.html"), "a"));
+		assert!(cache.is_fresh(&build_path, Path::new("blog.html"), "a"));
+		assert!(build_path.join("blog.html").exists());
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+
+	#[test]
+	fn interrupted_builds_leave_no_cache() {
+		let dir = test_dir("interrupted");
+		let build_path = dir.join("build");
+		let cache_path = dir.join(CACHE_FILENAME);
+
+		let cache = BuildCache::load(cache_path.clone()).unwrap();
+		write(&cache, &build_path, "index.html", "a");
+		cache.finish(&build_path).unwrap();
+		assert!(cache_path.exists());
+
+		// the cache is only written back once the build ends, so if it never does everything is rebuilt
+		let cache = BuildCache::load(cache_path.clone()).unwrap();
+		write(&cache, &build_path, "index.html", "b");
+		assert!(!cache_path.exists());
+		assert!(BuildCache::load(cache_path).unwrap().is_empty());
+
+		std::fs::remove_dir_all(dir).unwrap();
+	}
+}
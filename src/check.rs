@@ -0,0 +1,175 @@
+//! Module containing the internal link checker.
+
+use std::{
+	collections::{BTreeMap, HashSet},
+	fmt::Display,
+	path::{Path, PathBuf},
+};
+
+use eyre::Context;
+use kuchikiki::traits::*;
+use url::Url;
+use walkdir::WalkDir;
+
+use crate::{builder::SiteBuilder, util};
+
+/// Why a link is broken.
+#[derive(Debug)]
+pub enum BrokenLinkReason {
+	/// The linked file doesn't exist.
+	MissingTarget,
+	/// The linked file exists, but doesn't contain the linked anchor.
+	MissingAnchor,
+}
+
+/// A broken internal link found in the build output.
+#[derive(Debug)]
+pub struct BrokenLink {
+	/// The page containing the link, relative to the build directory.
+	pub page: PathBuf,
+	/// The link as written in the page.
+	pub link: String,
+	/// Why the link is broken.
+	pub reason: BrokenLinkReason,
+}
+
+impl Display for BrokenLink {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		let reason = match self.reason {
+			BrokenLinkReason::MissingTarget => "missing target",
+			BrokenLinkReason::MissingAnchor => "missing anchor",
+		};
+		write!(f, "{}: {} ({reason})", self.page.display(), self.link)
+	}
+}
+
+/// The links and anchors in a single HTML file.
+#[derive(Debug, Default)]
+struct HtmlFile {
+	/// Every `href` and `src` in the file.
+	links: Vec<String>,
+	/// Every anchor which can be linked to in the file.
+	anchors: HashSet<String>,
+}
+
+impl HtmlFile {
+	/// Reads the links and anchors from the HTML file at the given path.
+	fn read(path: &Path) -> eyre::Result<Self> {
+		let html = std::fs::read_to_string(path)
+			.with_context(|| format!("Failed to read {}", path.display()))?;
+		let document = kuchikiki::parse_html().one(html).document_node;
+		let mut file = Self::default();
+		for el in document
+			.select("[href], [src], [id], a[name]")
+			.expect("selector should be valid")
+		{
+			let attributes = el.attributes.borrow();
+			for attr in ["href", "src"] {
+				if let Some(link) = attributes.get(attr) {
+					file.links.push(link.to_string());
+				}
+			}
+			if let Some(id) = attributes.get("id") {
+				file.anchors.insert(id.to_string());
+			}
+			if el.name.local.as_ref() == "a"
+				&& let Some(name) = attributes.get("name")
+			{
+				file.anchors.insert(name.to_string());
+			}
+		}
+		Ok(file)
+	}
+}
+
+/// Gets the URL path an HTML file in the build directory is served at.
+fn served_path(file: &Path) -> String {
+	let path = util::template_name(file);
+	if path == "index.html" {
+		String::new()
+	} else if let Some(dir) = path.strip_suffix("/index.html") {
+		format!("{dir}/")
+	} else {
+		path.strip_suffix(".html").unwrap_or(&path).to_string()
+	}
+}
+
+/// Checks the site's build output for broken internal links and anchors.
+pub fn check_links(builder: &SiteBuilder) -> eyre::Result<Vec<BrokenLink>> {
+	let build_path = &builder.build_path;
+	let base_url = &builder.site.config.base_url;
+
+	let mut files = BTreeMap::new();
+	for entry in WalkDir::new(build_path).sort_by_file_name() {
+		let entry = entry?;
+		if entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "html")
+		{
+			files.insert(entry.path().to_owned(), HtmlFile::read(entry.path())?);
+		}
+	}
+
+	let mut broken = Vec::new();
+	for (path, file) in &files {
+		let page = path.strip_prefix(build_path)?;
+		let page_url = base_url.join(&served_path(page))?;
+		let mut checked = HashSet::new();
+		for link in &file.links {
+			if !checked.insert(link) {
+				continue;
+			}
+			let Some(url_path) = internal_path(base_url, &page_url, link) else {
+				continue;
+			};
+			let url_path = percent_encoding::percent_decode_str(&url_path).decode_utf8_lossy();
+			let target = util::resolve_build_path(build_path, &url_path);
+			let reason = if !target.is_file() {
+				Some(BrokenLinkReason::MissingTarget)
+			} else if let Ok(url) = page_url.join(link)
+				&& let Some(fragment) = url.fragment()
+				&& !fragment.is_empty()
+				&& fragment != "top"
+				&& let Some(target) = files.get(&target)
+				&& !target.anchors.contains(
+					percent_encoding::percent_decode_str(fragment)
+						.decode_utf8_lossy()
+						.as_ref(),
+				) {
+				Some(BrokenLinkReason::MissingAnchor)
+			} else {
+				None
+			};
+			if let Some(reason) = reason {
+				broken.push(BrokenLink {
+					page: page.to_owned(),
+					link: link.clone(),
+					reason,
+				});
+			}
+		}
+	}
+
+	Ok(broken)
+}
+
+/// Gets the path of a link relative to the site root, or `None` if it's not an internal link.
+fn internal_path(base_url: &Url, page_url: &Url, link: &str) -> Option<String> {
+	let url = page_url.join(link).ok()?;
+	if url.origin() != base_url.origin() {
+		return None;
+	}
+	url.path()
+		.strip_prefix(base_url.path())
+		.map(|path| path.to_string())
+}
+
+/// Fails with a report of the given broken links, if there are any.
+pub fn report(broken: &[BrokenLink]) -> eyre::Result<()> {
+	if broken.is_empty() {
+		return Ok(());
+	}
+	let mut report = format!("found {} broken link(s):", broken.len());
+	for link in broken {
+		report.push_str(&format!("\n  {link}"));
+	}
+	eyre::bail!(report)
+}
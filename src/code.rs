@@ -0,0 +1,361 @@
+//! Module containing the syntax highlighting used for code blocks.
+
+use std::ops::RangeInclusive;
+
+use eyre::Context;
+use serde::{Deserialize, Serialize};
+use syntect::{
+	easy::HighlightLines,
+	highlighting::Theme,
+	html::{ClassStyle, ClassedHTMLGenerator, IncludeBackground},
+	util::LinesWithEndings,
+};
+
+use crate::{builder::SiteBuilder, cache::CacheKey, util};
+
+/// Filename for the code theme stylesheet, stored in the webdog path.
+pub const CODE_THEME_FILENAME: &str = "code-theme.css";
+/// Filename for the dark code theme stylesheet, stored in the webdog path.
+pub const CODE_THEME_DARK_FILENAME: &str = "code-theme-dark.css";
+/// The class style used for class-based highlighting.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "wd-" };
+
+/// How code blocks are highlighted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CodeStyle {
+	/// Colors are inlined into each code block's HTML.
+	#[default]
+	Inline,
+	/// Code blocks are given CSS classes, styled by stylesheets generated from the code themes.
+	Classes,
+}
+
+/// Gets a theme by name, failing if it doesn't exist.
+fn get_theme<'b>(builder: &'b SiteBuilder, name: &str) -> eyre::Result<&'b Theme> {
+	builder
+		.theme_set
+		.themes
+		.get(name)
+		.ok_or_else(|| eyre::eyre!("missing code theme: {name}"))
+}
+
+/// Info about a fenced code block, parsed from its info string.
+///
+/// i.e. ```` ```rust title="main.rs" linenos hl_lines="3-5 7" ````
+#[derive(Debug, Default)]
+struct CodeBlockInfo {
+	/// The code block's language.
+	language: String,
+	/// The code block's title, such as a filename.
+	title: Option<String>,
+	/// Whether to show line numbers.
+	line_numbers: bool,
+	/// Ranges of lines to highlight, starting from 1.
+	highlighted_lines: Vec<RangeInclusive<usize>>,
+}
+
+impl CodeBlockInfo {
+	/// Parses a code block's info string.
+	fn parse(info: &str) -> eyre::Result<Self> {
+		let mut result = Self::default();
+		for (i, token) in tokenize(info).into_iter().enumerate() {
+			let (name, value) = match token.split_once('=') {
+				Some((name, value)) => (name, Some(value)),
+				None => (token.as_str(), None),
+			};
+			match (name, value) {
+				(language, None) if i == 0 => result.language = language.to_string(),
+				("title", Some(title)) => result.title = Some(title.to_string()),
+				("linenos", None) => result.line_numbers = true,
+				("hl_lines", Some(lines)) => {
+					for range in lines.split([' ', ',']).filter(|r| !r.is_empty()) {
+						let (start, end) = range.split_once('-').unwrap_or((range, range));
+						let parse = |line: &str| {
+							line.trim()
+								.parse::<usize>()
+								.wrap_err_with(|| format!("invalid line range: {range}"))
+						};
+						let (start, end) = (parse(start)?, parse(end)?);
+						// reversed ranges, i.e. `5-3`, highlight the same lines as their forward versions
+						result
+							.highlighted_lines
+							.push(start.min(end)..=start.max(end));
+					}
+				}
+				// other tools' attributes may be present, so unknown ones are ignored
+				_ => {}
+			}
+		}
+		Ok(result)
+	}
+
+	/// Whether the given line, starting from 1, should be highlighted.
+	fn is_highlighted(&self, line: usize) -> bool {
+		self.highlighted_lines
+			.iter()
+			.any(|range| range.contains(&line))
+	}
+}
+
+/// Splits an info string on whitespace, keeping quoted values together and removing their quotes.
+fn tokenize(info: &str) -> Vec<String> {
+	let mut tokens = Vec::new();
+	let mut token = String::new();
+	let mut quoted = false;
+	for c in info.chars() {
+		match c {
+			'"' => quoted = !quoted,
+			c if c.is_whitespace() && !quoted => {
+				if !token.is_empty() {
+					tokens.push(std::mem::take(&mut token));
+				}
+			}
+			c => token.push(c),
+		}
+	}
+	if !token.is_empty() {
+		tokens.push(token);
+	}
+	tokens
+}
+
+/// Splits highlighted HTML into lines without their line endings, closing and reopening any spans which cross between lines.
+fn split_lines(html: &str) -> Vec<String> {
+	let mut lines = Vec::new();
+	let mut open = Vec::new();
+	let mut line = String::new();
+	let mut has_text = false;
+	let mut rest = html;
+	while let Some(c) = rest.chars().next() {
+		if rest.starts_with("<span") {
+			let end = rest.find('>').map_or(rest.len(), |i| i + 1);
+			open.push(&rest[..end]);
+			line.push_str(&rest[..end]);
+			rest = &rest[end..];
+		} else if let Some(after) = rest.strip_prefix("</span>") {
+			open.pop();
+			line.push_str("</span>");
+			rest = after;
+		} else if c == '\n' {
+			line.push_str(&"</span>".repeat(open.len()));
+			lines.push(std::mem::take(&mut line));
+			line.extend(open.iter().copied());
+			has_text = false;
+			rest = &rest[1..];
+		} else {
+			line.push(c);
+			has_text = true;
+			rest = &rest[c.len_utf8()..];
+		}
+	}
+	if has_text {
+		lines.push(line);
+	}
+	lines
+}
+
+/// Highlights some code in the given language, falling back to plain text if the language is unknown.
+///
+/// Returns the opening `<pre>` tag along with the HTML for each line.
+fn highlight(
+	builder: &SiteBuilder,
+	code: &str,
+	language: &str,
+) -> eyre::Result<(String, Vec<String>)> {
+	let (syntax, syntax_set) = match builder.custom_syntax_set.find_syntax_by_token(language) {
+		Some(syntax) => (syntax, &builder.custom_syntax_set),
+		None => (
+			builder
+				.syntax_set
+				.find_syntax_by_token(language)
+				.unwrap_or_else(|| builder.syntax_set.find_syntax_plain_text()),
+			&builder.syntax_set,
+		),
+	};
+	match builder.site.config.code_style {
+		CodeStyle::Inline => {
+			let theme = get_theme(builder, &builder.site.config.code_theme)?;
+			let mut highlighter = HighlightLines::new(syntax, theme);
+			let (pre, background) = syntect::html::start_highlighted_html_snippet(theme);
+			let mut html = String::new();
+			for line in LinesWithEndings::from(code) {
+				let regions = highlighter.highlight_line(line, syntax_set)?;
+				html.push_str(&syntect::html::styled_line_to_highlighted_html(
+					&regions,
+					IncludeBackground::IfDifferent(background),
+				)?);
+			}
+			Ok((pre, split_lines(&html)))
+		}
+		CodeStyle::Classes => {
+			let mut generator =
+				ClassedHTMLGenerator::new_with_class_style(syntax, syntax_set, CLASS_STYLE);
+			for line in LinesWithEndings::from(code) {
+				generator.parse_html_for_line_which_includes_newline(line)?;
+			}
+			Ok((
+				r#"<pre class="wd-code">"#.to_string(),
+				split_lines(&generator.finalize()),
+			))
+		}
+	}
+}
+
+/// Renders a fenced code block with the given info string.
+pub(crate) fn render_code_block(
+	builder: &SiteBuilder,
+	code: &str,
+	info: &str,
+) -> eyre::Result<String> {
+	let info = CodeBlockInfo::parse(info)
+		.wrap_err_with(|| format!("Failed to parse code block info string: {info}"))?;
+	let (mut html, lines) = highlight(builder, code, &info.language)?;
+	let annotated = info.line_numbers || !info.highlighted_lines.is_empty();
+	for (i, line) in lines.iter().enumerate() {
+		if annotated {
+			let number = i + 1;
+			let class = if info.is_highlighted(number) {
+				"wd-line wd-hl"
+			} else {
+				"wd-line"
+			};
+			html.push_str(&format!(
+				r#"<span class="{class}" data-line="{number}">{line}</span>"#
+			));
+		} else {
+			html.push_str(line);
+		}
+		html.push('\n');
+	}
+	html.push_str("</pre>");
+
+	let class = if info.line_numbers {
+		"wd-codeblock wd-linenos"
+	} else {
+		"wd-codeblock"
+	};
+	let title = match &info.title {
+		Some(title) => format!(
+			r#"<div class="wd-code-title">{}</div>"#,
+			util::escape_attribute(title)
+		),
+		None => String::new(),
+	};
+	Ok(format!(
+		r#"<div class="{class}">
+			{title}
+			<button class="copy">Copy</button>
+			{html}
+		</div>"#
+	))
+}
+
+/// Writes the stylesheets for the configured code themes when using class-based highlighting.
+pub(crate) fn build_code_themes(builder: &SiteBuilder, webdog_path: &str) -> eyre::Result<()> {
+	if builder.site.config.code_style != CodeStyle::Classes {
+		return Ok(());
+	}
+	let out_path = builder.build_path.join(webdog_path);
+	let mut themes = vec![(&builder.site.config.code_theme, CODE_THEME_FILENAME)];
+	if let Some(dark) = &builder.site.config.code_theme_dark {
+		themes.push((dark, CODE_THEME_DARK_FILENAME));
+	}
+	for (theme, filename) in themes {
+		let css =
+			syntect::html::css_for_theme_with_class_style(get_theme(builder, theme)?, CLASS_STYLE)?;
+		builder.write_asset(&out_path.join(filename), CacheKey::new().add(&css), || {
+			Ok(css)
+		})?;
+	}
+	Ok(())
+}
+
+/// Gets the link tags for the code theme stylesheets, switching between them with `prefers-color-scheme` if there's a dark theme.
+pub(crate) fn theme_links(builder: &SiteBuilder, webdog_path: &str) -> Vec<String> {
+	if builder.site.config.code_style != CodeStyle::Classes {
+		return Vec::new();
+	}
+	let light = builder.asset_url(&format!("/{webdog_path}/{CODE_THEME_FILENAME}"));
+	if builder.site.config.code_theme_dark.is_some() {
+		let dark = builder.asset_url(&format!("/{webdog_path}/{CODE_THEME_DARK_FILENAME}"));
+		vec![
+			format!(
+				r#"<link rel="stylesheet" href="{light}" media="(prefers-color-scheme: light)">"#
+			),
+			format!(
+				r#"<link rel="stylesheet" href="{dark}" media="(prefers-color-scheme: dark)">"#
+			),
+		]
+	} else {
+		vec![format!(r#"<link rel="stylesheet" href="{light}">"#)]
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{CodeBlockInfo, split_lines};
+
+	#[test]
+	fn parses_info() {
+		let info =
+			CodeBlockInfo::parse(r#"rust title="src/main.rs" linenos hl_lines="1 3-4""#).unwrap();
+		assert_eq!(info.language, "rust");
+		assert_eq!(info.title.as_deref(), Some("src/main.rs"));
+		assert!(info.line_numbers);
+		assert_eq!(info.highlighted_lines, vec![1..=1, 3..=4]);
+
+		let info = CodeBlockInfo::parse("").unwrap();
+		assert_eq!(info.language, "");
+		assert_eq!(info.title, None);
+		assert!(!info.line_numbers);
+		assert!(info.highlighted_lines.is_empty());
+	}
+
+	#[test]
+	fn parses_quoted_titles() {
+		let info = CodeBlockInfo::parse(r#"sh title="run it.sh""#).unwrap();
+		assert_eq!(info.title.as_deref(), Some("run it.sh"));
+		// only the first token is the language
+		let info = CodeBlockInfo::parse("linenos rust").unwrap();
+		assert_eq!(info.language, "linenos");
+		assert!(!info.line_numbers);
+	}
+
+	#[test]
+	fn ignores_unknown_attributes() {
+		let info = CodeBlockInfo::parse(r#"js {.class} data-x="1" linenos"#).unwrap();
+		assert_eq!(info.language, "js");
+		assert!(info.line_numbers);
+	}
+
+	#[test]
+	fn parses_highlighted_lines() {
+		let info = CodeBlockInfo::parse(r#"rust hl_lines="2,4-5 7""#).unwrap();
+		assert_eq!(info.highlighted_lines, vec![2..=2, 4..=5, 7..=7]);
+		assert!(!info.is_highlighted(1));
+		assert!(info.is_highlighted(2));
+		assert!(info.is_highlighted(5));
+		assert!(!info.is_highlighted(6));
+
+		let info = CodeBlockInfo::parse(r#"rust hl_lines="5-3""#).unwrap();
+		assert_eq!(info.highlighted_lines, vec![3..=5]);
+		assert!(info.is_highlighted(4));
+
+		assert!(CodeBlockInfo::parse(r#"rust hl_lines="x""#).is_err());
+		assert!(CodeBlockInfo::parse(r#"rust hl_lines="1-""#).is_err());
+	}
+
+	#[test]
+	fn splits_lines() {
+		assert_eq!(split_lines("a\nb\n"), vec!["a", "b"]);
+		assert_eq!(
+			split_lines("<span class=\"s\">\"a\nb\"</span>\n"),
+			vec![
+				r#"<span class="s">"a</span>"#,
+				r#"<span class="s">b"</span>"#
+			]
+		);
+		assert_eq!(split_lines("a\n\nb"), vec!["a", "", "b"]);
+	}
+}
@@ -0,0 +1,259 @@
+//! Module containing the template dependency graph used for targeted rebuilds.
+
+use std::collections::{BTreeSet, HashMap};
+
+use tera::{Tera, ast::Node};
+
+/// A build output which depends on templates.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Dependent {
+	/// A standard page, by name.
+	Page(String),
+	/// Every output of a resource type, by prefix.
+	Resources(String),
+}
+
+/// Graph of which templates each of the site's outputs depend on.
+#[derive(Debug, Default)]
+pub struct DependencyGraph {
+	/// Map of outputs to the templates they depend on.
+	dependencies: HashMap<Dependent, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+	/// Sets the templates the given output depends on, replacing any previous dependencies.
+	pub fn set(&mut self, dependent: Dependent, templates: BTreeSet<String>) {
+		self.dependencies.insert(dependent, templates);
+	}
+
+	/// Removes an output from the graph.
+	#[cfg(feature = "serve")]
+	pub fn remove(&mut self, dependent: &Dependent) {
+		self.dependencies.remove(dependent);
+	}
+
+	/// Gets every output which depends on the given template, in a stable order.
+	pub fn dependents(&self, template: &str) -> Vec<Dependent> {
+		let mut dependents: Vec<_> = self
+			.dependencies
+			.iter()
+			.filter(|(_, templates)| templates.contains(template))
+			.map(|(dependent, _)| dependent.clone())
+			.collect();
+		dependents.sort();
+		dependents
+	}
+}
+
+/// Finds the templates referenced by `wd-partial` tags in the given HTML or Markdown.
+pub fn find_partials(input: &str) -> Vec<String> {
+	let mut partials = Vec::new();
+	let mut rest = input;
+	while let Some(start) = rest.find("<wd-partial") {
+		rest = &rest[start + "<wd-partial".len()..];
+		// tags which only start with the same name aren't partials
+		if !rest.starts_with(|c: char| c.is_whitespace() || c == '>' || c == '/') {
+			continue;
+		}
+		if let Some(template) = partial_template(rest) {
+			partials.push(template.to_string());
+		}
+	}
+	partials
+}
+
+/// Gets the value of the `t` attribute from the rest of a `wd-partial` tag after its name.
+fn partial_template(tag: &str) -> Option<&str> {
+	let mut rest = tag;
+	loop {
+		rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == '/');
+		if rest.is_empty() || rest.starts_with('>') {
+			return None;
+		}
+		let name_end = rest
+			.find(|c: char| c.is_whitespace() || matches!(c, '=' | '>' | '/'))
+			.unwrap_or(rest.len());
+		let name = &rest[..name_end];
+		rest = rest[name_end..].trim_start();
+		let mut value = None;
+		if let Some(after) = rest.strip_prefix('=') {
+			let after = after.trim_start();
+			// other attributes' values are skipped over whole, so a `t=` inside them isn't mistaken for the template
+			let end = match after.chars().next() {
+				Some(quote @ ('"' | '\'')) => {
+					let end = after[1..].find(quote)? + 1;
+					value = Some(&after[1..end]);
+					end + 1
+				}
+				_ => {
+					let end = after
+						.find(|c: char| c.is_whitespace() || c == '>')
+						.unwrap_or(after.len());
+					value = Some(&after[..end]);
+					end
+				}
+			};
+			rest = &after[end..];
+		}
+		if name.eq_ignore_ascii_case("t") {
+			return value;
+		}
+	}
+}
+
+/// Collects the given templates and every template they depend on through `extends`, `include`, `import` and `wd-partial`.
+pub fn collect_templates<'t>(
+	tera: &Tera,
+	templates: impl IntoIterator<Item = &'t str>,
+) -> BTreeSet<String> {
+	let mut found = BTreeSet::new();
+	let mut queue: Vec<String> = templates.into_iter().map(|t| t.to_string()).collect();
+	while let Some(name) = queue.pop() {
+		if !found.insert(name.clone()) {
+			continue;
+		}
+		let Ok(template) = tera.get_template(&name) else {
+			continue;
+		};
+		queue.extend(template.parents.iter().cloned());
+		queue.extend(
+			template
+				.imported_macro_files
+				.iter()
+				.map(|(file, _)| file.clone()),
+		);
+		let mut nodes: Vec<&Node> = template.ast.iter().collect();
+		for definition in template.macros.values() {
+			nodes.extend(definition.body.iter());
+		}
+		while let Some(node) = nodes.pop() {
+			match node {
+				Node::Text(text) => queue.extend(find_partials(text)),
+				Node::Extends(_, parent) => queue.push(parent.clone()),
+				Node::Include(_, includes, _) => queue.extend(includes.iter().cloned()),
+				Node::ImportMacro(_, file, _) => queue.push(file.clone()),
+				Node::MacroDefinition(_, definition, _) => nodes.extend(definition.body.iter()),
+				Node::FilterSection(_, section, _) => nodes.extend(section.body.iter()),
+				Node::Block(_, block, _) => nodes.extend(block.body.iter()),
+				Node::Forloop(_, forloop, _) => {
+					nodes.extend(forloop.body.iter());
+					if let Some(empty_body) = &forloop.empty_body {
+						nodes.extend(empty_body.iter());
+					}
+				}
+				Node::If(conditions, _) => {
+					for (_, _, body) in &conditions.conditions {
+						nodes.extend(body.iter());
+					}
+					if let Some((_, body)) = &conditions.otherwise {
+						nodes.extend(body.iter());
+					}
+				}
+				_ => {}
+			}
+		}
+	}
+	found
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::BTreeSet;
+
+	use tera::Tera;
+
+	use super::{collect_templates, find_partials};
+
+	#[test]
+	fn finds_quoted_partials() {
+		assert_eq!(
+			find_partials(r#"<wd-partial t="a.tera"></wd-partial>"#),
+			vec!["a.tera"]
+		);
+		assert_eq!(
+			find_partials("<p>hi</p><wd-partial t='b.tera' />"),
+			vec!["b.tera"]
+		);
+		assert_eq!(
+			find_partials("<wd-partial\n\tt = \"c.tera\"\n>"),
+			vec!["c.tera"]
+		);
+		assert_eq!(find_partials("<wd-partial t=d.tera>"), vec!["d.tera"]);
+		assert_eq!(
+			find_partials(r#"<wd-partial t="e.tera"></wd-partial> <wd-partial t="f.tera">"#),
+			vec!["e.tera", "f.tera"]
+		);
+	}
+
+	#[test]
+	fn ignores_t_inside_other_attributes() {
+		assert_eq!(
+			find_partials(r#"<wd-partial data-t="no.tera" t="yes.tera">"#),
+			vec!["yes.tera"]
+		);
+		assert_eq!(
+			find_partials(r#"<wd-partial title="a t='no.tera'" t="yes.tera">"#),
+			vec!["yes.tera"]
+		);
+		assert_eq!(
+			find_partials(r#"<wd-partial at="no.tera">"#),
+			Vec::<String>::new()
+		);
+		assert_eq!(
+			find_partials(r#"<wd-partials t="no.tera">"#),
+			Vec::<String>::new()
+		);
+	}
+
+	#[test]
+	fn handles_unclosed_tags() {
+		assert_eq!(find_partials(r#"<wd-partial t="a.tera""#), vec!["a.tera"]);
+		assert_eq!(
+			find_partials(r#"<wd-partial t="a.tera"#),
+			Vec::<String>::new()
+		);
+		// attributes of whatever comes after a tag's end aren't part of it
+		assert_eq!(
+			find_partials(r#"<wd-partial><p t="no.tera">"#),
+			Vec::<String>::new()
+		);
+	}
+
+	#[test]
+	fn collects_nested_templates() {
+		let mut tera = Tera::default();
+		tera.add_raw_templates(vec![
+			("base.tera", "{% block content %}{% endblock content %}"),
+			(
+				"page.tera",
+				r#"{% extends "base.tera" %}{% import "macros.tera" as macros %}{% block content %}{% include "header.tera" %}{% endblock content %}"#,
+			),
+			("header.tera", r#"{% if true %}{% include "nav.tera" %}{% endif %}"#),
+			(
+				"nav.tera",
+				r#"{% for i in [1] %}<wd-partial t="link.tera"></wd-partial>{% endfor %}"#,
+			),
+			("link.tera", "<a></a>"),
+			("macros.tera", "{% macro m() %}{% endmacro m %}"),
+			("unused.tera", "<p></p>"),
+		])
+		.unwrap();
+
+		let expected: BTreeSet<String> = [
+			"base.tera",
+			"page.tera",
+			"header.tera",
+			"nav.tera",
+			"link.tera",
+			"macros.tera",
+		]
+		.into_iter()
+		.map(String::from)
+		.collect();
+		assert_eq!(collect_templates(&tera, ["page.tera"]), expected);
+		assert_eq!(
+			collect_templates(&tera, ["link.tera"]),
+			BTreeSet::from(["link.tera".to_string()])
+		);
+	}
+}
@@ -4,15 +4,59 @@
   console.log("Connecting...");
 
   let socket;
+  let overlay;
+
+  function hideError() {
+    if (overlay) {
+      overlay.remove();
+      overlay = null;
+    }
+  }
+
+  function showError(error) {
+    hideError();
+    overlay = document.createElement("div");
+    overlay.style.cssText =
+      "position: fixed; inset: 0; z-index: 2147483647; overflow: auto; padding: 2rem; background: rgba(20, 10, 20, 0.92); color: #fff; font-family: monospace; font-size: 14px;";
+
+    const header = document.createElement("div");
+    header.style.cssText = "display: flex; justify-content: space-between; gap: 1rem; margin-bottom: 1rem;";
+    const title = document.createElement("strong");
+    title.style.color = "#ff8fa3";
+    let where = error.file || "site";
+    if (error.file && error.line) where += `:${error.line}`;
+    title.textContent = `Failed to build ${where}`;
+    const close = document.createElement("button");
+    close.textContent = "Close";
+    close.addEventListener("click", hideError);
+    header.append(title, close);
+
+    const message = document.createElement("pre");
+    message.style.cssText = "margin: 0; white-space: pre-wrap;";
+    message.textContent = error.message;
+
+    overlay.append(header, message);
+    if (document.body) {
+      document.body.append(overlay);
+    } else {
+      const pending = overlay;
+      document.addEventListener("DOMContentLoaded", () => document.body.append(pending));
+    }
+  }
 
   function start(reload) {
     socket = new WebSocket(`ws://${location.host}`);
     let reloading = false;
     socket.onmessage = function (ev) {
-      if (ev.data === "reload") {
+      const message = JSON.parse(ev.data);
+      if (message.type === "reload") {
         reloading = true;
+        hideError();
         console.log("Reloading...");
         location.reload();
+      } else if (message.type === "error") {
+        console.error(message.message);
+        showError(message);
       }
     };
     socket.onclose = function () {
@@ -0,0 +1,82 @@
+(function () {
+  "use strict";
+
+  const indexUrl = new URL("search-index.json", document.currentScript.src);
+  let index = null;
+
+  function loadIndex() {
+    if (!index) {
+      index = fetch(indexUrl)
+        .then((res) => res.json())
+        .then((entries) =>
+          entries.map((entry) => ({
+            entry,
+            title: entry.title.toLowerCase(),
+            desc: (entry.desc || "").toLowerCase(),
+            tags: (entry.tags || []).map((tag) => tag.toLowerCase()),
+            text: entry.text.toLowerCase(),
+          })),
+        );
+    }
+    return index;
+  }
+
+  function score(item, terms) {
+    let total = 0;
+    for (const term of terms) {
+      let termScore = 0;
+      if (item.title.includes(term)) termScore += 10;
+      if (item.tags.some((tag) => tag.includes(term))) termScore += 5;
+      if (item.desc.includes(term)) termScore += 3;
+      if (item.text.includes(term)) termScore += 1;
+      // every term must match somewhere
+      if (termScore === 0) return 0;
+      total += termScore;
+    }
+    return total;
+  }
+
+  function snippet(item, terms) {
+    if (item.entry.desc) return item.entry.desc;
+    const found = terms.map((term) => item.text.indexOf(term)).filter((i) => i >= 0);
+    const position = found.length > 0 ? Math.max(0, Math.min(...found) - 40) : 0;
+    const text = item.entry.text.slice(position, position + 160);
+    return (position > 0 ? "…" : "") + text + (position + 160 < item.text.length ? "…" : "");
+  }
+
+  for (const search of document.querySelectorAll(".wd-search")) {
+    const input = search.querySelector("input");
+    const results = search.querySelector(".wd-search-results");
+    const limit = parseInt(search.dataset.limit || "10", 10);
+    input.addEventListener("focus", loadIndex, { once: true });
+    input.addEventListener("input", async () => {
+      const query = input.value.trim().toLowerCase();
+      const terms = query.split(/\s+/).filter((term) => term);
+      if (terms.length === 0) {
+        results.replaceChildren();
+        return;
+      }
+      const items = await loadIndex();
+      // skip stale results if the query changed while loading
+      if (input.value.trim().toLowerCase() !== query) return;
+
+      const matches = items
+        .map((item) => [item, score(item, terms)])
+        .filter(([, itemScore]) => itemScore > 0)
+        .sort((a, b) => b[1] - a[1])
+        .slice(0, limit);
+      results.replaceChildren(
+        ...matches.map(([item]) => {
+          const li = document.createElement("li");
+          const a = document.createElement("a");
+          a.href = item.entry.url;
+          a.textContent = item.entry.title;
+          const p = document.createElement("p");
+          p.textContent = snippet(item, terms);
+          li.append(a, p);
+          return li;
+        }),
+      );
+    });
+  }
+})();
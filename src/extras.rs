@@ -82,6 +82,8 @@ fn resource_list_outside(
 		template: String,
 		resource: String,
 		count: usize,
+		#[serde(default)]
+		language: Option<String>,
 	}
 
 	#[derive(Debug, Serialize)]
@@ -96,27 +98,24 @@ fn resource_list_outside(
 		.get(&data.resource)
 		.ok_or_else(|| eyre::eyre!("missing resource builder: {}", data.resource))?;
 
+	// only resources in the requested language are listed, defaulting to the site's default language
+	let language = data.language.as_deref().or(builder
+		.site
+		.config
+		.i18n
+		.as_ref()
+		.map(|i18n| i18n.default_language.as_str()));
+
 	let resource_list = builder.tera.render(
 		&data.template,
 		&tera::Context::from_serialize(ResourceListTemplateData {
 			resources: res_builder
 				.loaded_metadata
 				.iter()
+				.enumerate()
+				.filter(|(_, (_, v))| v.data().language.as_deref() == language)
 				.take(data.count)
-				.map(|(id, v)| {
-					crate::util::format_timestamp(
-						v.data().timestamp,
-						&res_builder.config.timestamp_format,
-					)
-					.map(|ts| (id, v, ts))
-				})
-				.map(|v| {
-					v.map(|(id, v, ts)| ResourceTemplateData {
-						resource: v,
-						id: id.clone(),
-						readable_timestamp: ts,
-					})
-				})
+				.map(|(index, _)| res_builder.template_data(builder, index))
 				.collect::<eyre::Result<Vec<_>>>()?,
 		})?,
 	)?;
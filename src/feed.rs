@@ -0,0 +1,331 @@
+//! Module containing the builders for resource feeds.
+
+use atom_syndication::{
+	CategoryBuilder, ContentBuilder, EntryBuilder, FeedBuilder, FixedDateTime, LinkBuilder,
+	PersonBuilder,
+};
+use eyre::Context;
+use rss::{ChannelBuilder, ItemBuilder, validation::Validate};
+use serde::{Deserialize, Serialize};
+use time::{
+	OffsetDateTime,
+	format_description::well_known::{Rfc2822, Rfc3339},
+};
+use url::Url;
+
+use crate::{
+	AlternateLink,
+	builder::SiteBuilder,
+	cache::CacheKey,
+	resource::{ResourceRSSBuilderConfig, ResourceTemplateData},
+};
+
+/// Filename for RSS feeds.
+pub const RSS_FILENAME: &str = "rss.xml";
+/// Filename for Atom feeds.
+pub const ATOM_FILENAME: &str = "atom.xml";
+/// Filename for JSON feeds.
+pub const JSON_FEED_FILENAME: &str = "feed.json";
+
+/// Config for an additional feed format.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeedFormatConfig {
+	/// Template used when rendering each item's content. Defaults to the RSS feed's template.
+	#[serde(default)]
+	pub template: Option<String>,
+}
+
+/// Author info for a feed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FeedAuthor {
+	/// The author's name.
+	pub name: String,
+	/// The author's email address, if any.
+	#[serde(default)]
+	pub email: Option<String>,
+	/// The author's website, if any.
+	#[serde(default)]
+	pub url: Option<String>,
+}
+
+/// Information about a single set of feeds to build.
+pub(crate) struct FeedInfo<'a> {
+	/// The feed's title.
+	pub title: &'a str,
+	/// The feed's description.
+	pub description: &'a str,
+	/// Path to the HTML page the feed corresponds to, relative to the site root.
+	pub list_path: &'a str,
+	/// Path to the directory the feeds are written to, relative to the site root.
+	pub feed_path: &'a str,
+	/// The code of the language the feed's resources are written in, if the site is multilingual.
+	pub language: Option<&'a str>,
+}
+
+/// JSON Feed 1.1 document.
+#[derive(Debug, Serialize)]
+struct JsonFeed<'a> {
+	version: &'static str,
+	title: &'a str,
+	home_page_url: String,
+	feed_url: String,
+	#[serde(skip_serializing_if = "str::is_empty")]
+	description: &'a str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	language: Option<&'a str>,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	authors: Vec<JsonFeedAuthor<'a>>,
+	items: Vec<JsonFeedItem<'a>>,
+}
+
+/// Author of a JSON Feed document.
+#[derive(Debug, Serialize)]
+struct JsonFeedAuthor<'a> {
+	name: &'a str,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	url: Option<&'a str>,
+}
+
+/// Single item in a JSON Feed document.
+#[derive(Debug, Serialize)]
+struct JsonFeedItem<'a> {
+	id: String,
+	url: String,
+	title: &'a str,
+	content_html: String,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	summary: Option<&'a str>,
+	date_published: String,
+	#[serde(skip_serializing_if = "<[_]>::is_empty")]
+	tags: &'a [String],
+}
+
+/// Gets the alternate links for the feeds in the given directory, for `<link rel="alternate">` auto-discovery.
+pub(crate) fn feed_alternates(
+	config: &ResourceRSSBuilderConfig,
+	title: &str,
+	feed_path: &str,
+) -> Vec<AlternateLink> {
+	let mut alternates = vec![AlternateLink::feed(
+		format!("/{feed_path}/{RSS_FILENAME}"),
+		"application/rss+xml",
+		title,
+	)];
+	if config.atom.is_some() {
+		alternates.push(AlternateLink::feed(
+			format!("/{feed_path}/{ATOM_FILENAME}"),
+			"application/atom+xml",
+			title,
+		));
+	}
+	if config.json.is_some() {
+		alternates.push(AlternateLink::feed(
+			format!("/{feed_path}/{JSON_FEED_FILENAME}"),
+			"application/feed+json",
+			title,
+		));
+	}
+	alternates
+}
+
+/// Builds the configured feeds for the given resources.
+pub(crate) fn build_feeds(
+	builder: &SiteBuilder,
+	config: &ResourceRSSBuilderConfig,
+	key: &CacheKey,
+	info: FeedInfo,
+	resources: &[&ResourceTemplateData],
+) -> eyre::Result<()> {
+	let key = key
+		.clone()
+		.add(info.title)
+		.add(info.description)
+		.add(info.list_path)
+		.add(info.language.unwrap_or_default())
+		.add_serialized(&resources)?;
+	let out_path = builder.build_path.join(info.feed_path);
+	let base_url = &builder.site.config.base_url;
+	let list_url = base_url.join(&format!("{}/", info.list_path))?;
+	let resource_url = |resource: &ResourceTemplateData| -> eyre::Result<Url> {
+		Ok(base_url.join(resource.url.trim_start_matches('/'))?)
+	};
+	let render_content = |template: &str, resource: &ResourceTemplateData| {
+		builder
+			.tera
+			.render(template, &tera::Context::from_serialize(resource)?)
+			.wrap_err("Failed to render feed content")
+	};
+
+	// Build RSS feed
+	builder.write_output(&out_path.join(RSS_FILENAME), key.clone().add("rss"), || {
+		let mut items = Vec::with_capacity(resources.len());
+		for resource in resources {
+			let data = resource.resource.data();
+			items.push(
+				ItemBuilder::default()
+					.title(Some(data.title.to_owned()))
+					.link(Some(resource_url(resource)?.to_string()))
+					.description(data.desc.clone())
+					.pub_date(Some(data.timestamp.format(&Rfc2822)?))
+					.content(Some(render_content(&config.template, resource)?))
+					.build(),
+			)
+		}
+
+		let channel = ChannelBuilder::default()
+			.title(info.title.to_owned())
+			.link(list_url.clone())
+			.description(info.description.to_owned())
+			.language(info.language.map(str::to_owned))
+			.managing_editor(config.author.as_ref().and_then(|author| {
+				author
+					.email
+					.as_ref()
+					.map(|email| format!("{email} ({})", author.name))
+			}))
+			.last_build_date(Some(OffsetDateTime::now_utc().format(&Rfc2822)?))
+			.items(items)
+			.build();
+		channel.validate().wrap_err("Failed to validate RSS feed")?;
+		Ok(channel.to_string())
+	})?;
+
+	// Build Atom feed
+	if let Some(atom) = &config.atom {
+		builder.write_output(
+			&out_path.join(ATOM_FILENAME),
+			key.clone().add("atom"),
+			|| {
+				let template = atom.template.as_ref().unwrap_or(&config.template);
+				let mut entries = Vec::with_capacity(resources.len());
+				for resource in resources {
+					let data = resource.resource.data();
+					let url = resource_url(resource)?.to_string();
+					let timestamp = fixed_date_time(data.timestamp)?;
+					entries.push(
+						EntryBuilder::default()
+							.id(url.clone())
+							.title(data.title.clone())
+							.updated(timestamp)
+							.published(Some(timestamp))
+							.link(LinkBuilder::default().href(url).rel("alternate").build())
+							.summary(data.desc.clone().map(Into::into))
+							.content(Some(
+								ContentBuilder::default()
+									.value(Some(render_content(template, resource)?))
+									.content_type(Some("html".to_string()))
+									.build(),
+							))
+							.categories(
+								data.tags
+									.iter()
+									.map(|tag| CategoryBuilder::default().term(tag.clone()).build())
+									.collect::<Vec<_>>(),
+							)
+							.build(),
+					);
+				}
+
+				let updated = match resources.first() {
+					Some(resource) => resource.resource.data().timestamp,
+					None => OffsetDateTime::now_utc(),
+				};
+				let feed = FeedBuilder::default()
+					.id(list_url.to_string())
+					.lang(info.language.map(str::to_owned))
+					.title(info.title.to_owned())
+					.subtitle(
+						(!info.description.is_empty()).then(|| info.description.to_owned().into()),
+					)
+					.updated(fixed_date_time(updated)?)
+					.authors(
+						config
+							.author
+							.iter()
+							.map(|author| {
+								PersonBuilder::default()
+									.name(author.name.clone())
+									.email(author.email.clone())
+									.uri(author.url.clone())
+									.build()
+							})
+							.collect::<Vec<_>>(),
+					)
+					.link(
+						LinkBuilder::default()
+							.href(list_url.to_string())
+							.rel("alternate")
+							.build(),
+					)
+					.link(
+						LinkBuilder::default()
+							.href(
+								base_url
+									.join(&format!("{}/{ATOM_FILENAME}", info.feed_path))?
+									.to_string(),
+							)
+							.rel("self")
+							.build(),
+					)
+					.entries(entries)
+					.build();
+				Ok(feed.to_string())
+			},
+		)?;
+	}
+
+	// Build JSON feed
+	if let Some(json) = &config.json {
+		builder.write_output(
+			&out_path.join(JSON_FEED_FILENAME),
+			key.clone().add("json"),
+			|| {
+				let template = json.template.as_ref().unwrap_or(&config.template);
+				let mut items = Vec::with_capacity(resources.len());
+				for resource in resources {
+					let data = resource.resource.data();
+					let url = resource_url(resource)?.to_string();
+					items.push(JsonFeedItem {
+						id: url.clone(),
+						url,
+						title: &data.title,
+						content_html: render_content(template, resource)?,
+						summary: data.desc.as_deref(),
+						date_published: data.timestamp.format(&Rfc3339)?,
+						tags: &data.tags,
+					});
+				}
+
+				let feed = JsonFeed {
+					version: "https://jsonfeed.org/version/1.1",
+					title: info.title,
+					home_page_url: list_url.to_string(),
+					feed_url: base_url
+						.join(&format!("{}/{JSON_FEED_FILENAME}", info.feed_path))?
+						.to_string(),
+					description: info.description,
+					language: info.language,
+					authors: config
+						.author
+						.iter()
+						.map(|author| JsonFeedAuthor {
+							name: &author.name,
+							url: author.url.as_deref(),
+						})
+						.collect(),
+					items,
+				};
+				Ok(serde_json::to_string_pretty(&feed)?)
+			},
+		)?;
+	}
+
+	Ok(())
+}
+
+/// Converts a timestamp to the format used by the Atom feed builder.
+fn fixed_date_time(timestamp: OffsetDateTime) -> eyre::Result<FixedDateTime> {
+	Ok(FixedDateTime::parse_from_rfc3339(
+		&timestamp.format(&Rfc3339)?,
+	)?)
+}
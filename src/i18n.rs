@@ -0,0 +1,119 @@
+//! Module containing the config and helpers for multilingual sites.
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+
+/// Config for the languages of a multilingual site.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct I18nConfig {
+	/// The code of the site's default language, used for pages and resources without a language suffix.
+	pub default_language: String,
+	/// The site's languages, by their codes.
+	pub languages: BTreeMap<String, LanguageConfig>,
+}
+
+/// Config for a single language.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LanguageConfig {
+	/// The language's name, as written in the language itself.
+	pub name: String,
+	/// The path prefix the language's pages and resources are built under. Defaults to the language's code, or no prefix for the default language.
+	#[serde(default)]
+	pub prefix: Option<String>,
+	/// The site's title in the language. Defaults to the site's title.
+	#[serde(default)]
+	pub title: Option<String>,
+	/// The site's description in the language. Defaults to the site's description.
+	#[serde(default)]
+	pub description: Option<String>,
+	/// Translated strings available to templates, by key.
+	#[serde(default)]
+	pub strings: BTreeMap<String, String>,
+}
+
+impl I18nConfig {
+	/// Checks the language config for errors.
+	pub(crate) fn check(&self) -> eyre::Result<()> {
+		if !self.languages.contains_key(&self.default_language) {
+			eyre::bail!(
+				"default language {} is missing from the site's languages",
+				self.default_language
+			);
+		}
+		let mut prefixes = HashSet::new();
+		for code in self.languages.keys() {
+			let prefix = self.prefix(code);
+			if !prefixes.insert(prefix.clone()) {
+				eyre::bail!("multiple languages are built under the prefix /{prefix}");
+			}
+		}
+		Ok(())
+	}
+
+	/// Gets the path prefix for a language, with a trailing slash unless it's empty.
+	pub fn prefix(&self, language: &str) -> String {
+		let prefix = match self.languages.get(language).and_then(|l| l.prefix.as_ref()) {
+			Some(prefix) => prefix.trim_matches('/').to_string(),
+			None if language == self.default_language => String::new(),
+			None => language.to_string(),
+		};
+		if prefix.is_empty() {
+			prefix
+		} else {
+			format!("{prefix}/")
+		}
+	}
+
+	/// Splits the language suffix off the name of a source file without its extension, falling back to the default language.
+	///
+	/// i.e. `blog/about.fr` becomes `blog/about` and `fr`, and `blog/about` becomes `blog/about` and the default language.
+	pub fn split_language<'n>(&'n self, name: &'n str) -> (&'n str, &'n str) {
+		if let Some((base, language)) = name.rsplit_once('.')
+			&& !language.contains('/')
+			&& self.languages.contains_key(language)
+		{
+			return (base, language);
+		}
+		(name, &self.default_language)
+	}
+}
+
+/// The language a page is written in.
+#[derive(Debug, Clone)]
+pub struct PageLanguage {
+	/// The language's code.
+	pub language: String,
+	/// The name of the page without its language, shared by all of its translations.
+	pub base: String,
+}
+
+/// A translation of a page or resource, used to link between them.
+#[derive(Debug, Clone, Serialize)]
+pub struct Translation {
+	/// The translation's language code.
+	pub code: String,
+	/// The translation's language name.
+	pub name: String,
+	/// The URL the translation is served at.
+	pub url: String,
+}
+
+/// Template data for the language a page is rendered in.
+#[derive(Debug, Serialize)]
+pub struct LanguageTemplateData<'a> {
+	/// The language's code.
+	pub code: &'a str,
+	/// The language's name.
+	pub name: &'a str,
+	/// The path prefix the language's pages are built under, i.e. `/fr/`.
+	pub prefix: String,
+	/// The site's title in the language.
+	pub title: &'a str,
+	/// The site's description in the language.
+	pub description: &'a str,
+	/// Translated strings for the language, by key.
+	pub strings: &'a BTreeMap<String, String>,
+	/// Every translation of the page, including itself, or nothing if it hasn't been translated.
+	pub translations: &'a [Translation],
+}
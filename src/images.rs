@@ -0,0 +1,352 @@
+//! Module containing the image pipeline used to build responsive image variants.
+
+use std::{
+	collections::BTreeMap,
+	io::Cursor,
+	path::{Path, PathBuf},
+};
+
+use eyre::Context;
+use image::{DynamicImage, imageops::FilterType};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{ROOT_PATH, builder::SiteBuilder, cache::CacheKey, util};
+
+/// File extensions of images which can be read.
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+
+/// Config for the site's image pipeline.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageConfig {
+	/// Widths to resize images to. Widths larger than an image are skipped, and the image's own width is always included.
+	#[serde(default = "ImageConfig::default_widths")]
+	pub widths: Vec<u32>,
+	/// Formats to build variants in, in order of preference. The last format is used for the `img` tag itself.
+	#[serde(default = "ImageConfig::default_formats")]
+	pub formats: Vec<ImageFormat>,
+	/// Quality used for JPEG and AVIF variants, from 1 to 100. WebP variants are lossless and ignore it.
+	#[serde(default = "ImageConfig::default_quality")]
+	pub quality: u8,
+	/// The default `sizes` attribute for images which don't set their own.
+	#[serde(default = "ImageConfig::default_sizes")]
+	pub sizes: String,
+	/// Path to where image variants should be written to.
+	#[serde(default = "ImageConfig::default_output_path")]
+	pub output_path: String,
+}
+
+impl ImageConfig {
+	fn default_widths() -> Vec<u32> {
+		vec![480, 960, 1440]
+	}
+
+	fn default_formats() -> Vec<ImageFormat> {
+		vec![ImageFormat::Original]
+	}
+
+	fn default_quality() -> u8 {
+		80
+	}
+
+	fn default_sizes() -> String {
+		"100vw".to_string()
+	}
+
+	fn default_output_path() -> String {
+		"images".to_string()
+	}
+}
+
+/// Format for image variants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+	/// The same format as the source image.
+	Original,
+	/// JPEG, using the configured quality.
+	Jpeg,
+	/// PNG.
+	Png,
+	/// Lossless WebP, ignoring the configured quality.
+	Webp,
+	/// AVIF, using the configured quality. Requires webdog's `avif` feature.
+	Avif,
+}
+
+impl ImageFormat {
+	/// Gets the format of a source image from its file extension.
+	fn from_extension(extension: &str) -> Option<Self> {
+		match extension.to_lowercase().as_str() {
+			"jpg" | "jpeg" => Some(Self::Jpeg),
+			"png" => Some(Self::Png),
+			"webp" => Some(Self::Webp),
+			_ => None,
+		}
+	}
+
+	/// Resolves the original format to the given source format.
+	fn resolve(self, source: Self) -> Self {
+		match self {
+			Self::Original => source,
+			format => format,
+		}
+	}
+
+	/// The file extension used for the format.
+	fn extension(self) -> &'static str {
+		match self {
+			Self::Original => unreachable!("original format should be resolved"),
+			Self::Jpeg => "jpg",
+			Self::Png => "png",
+			Self::Webp => "webp",
+			Self::Avif => "avif",
+		}
+	}
+
+	/// The MIME type used for the format.
+	fn mime_type(self) -> &'static str {
+		match self {
+			Self::Original => unreachable!("original format should be resolved"),
+			Self::Jpeg => "image/jpeg",
+			Self::Png => "image/png",
+			Self::Webp => "image/webp",
+			Self::Avif => "image/avif",
+		}
+	}
+
+	/// Encodes an image in the format.
+	fn encode(self, image: &DynamicImage, quality: u8) -> eyre::Result<Vec<u8>> {
+		let mut out = Cursor::new(Vec::new());
+		match self {
+			Self::Original => unreachable!("original format should be resolved"),
+			Self::Jpeg => {
+				let encoder = image::codecs::jpeg::JpegEncoder::new_with_quality(&mut out, quality);
+				// JPEG has no alpha channel
+				DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+			}
+			Self::Png => {
+				image.write_with_encoder(image::codecs::png::PngEncoder::new(&mut out))?;
+			}
+			Self::Webp => {
+				let encoder = image::codecs::webp::WebPEncoder::new_lossless(&mut out);
+				if image.color().has_alpha() {
+					DynamicImage::ImageRgba8(image.to_rgba8()).write_with_encoder(encoder)?;
+				} else {
+					DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(encoder)?;
+				}
+			}
+			#[cfg(feature = "avif")]
+			Self::Avif => {
+				let encoder =
+					image::codecs::avif::AvifEncoder::new_with_speed_quality(&mut out, 6, quality);
+				image.write_with_encoder(encoder)?;
+			}
+			#[cfg(not(feature = "avif"))]
+			Self::Avif => eyre::bail!("AVIF images require webdog to be built with the avif feature"),
+		}
+		Ok(out.into_inner())
+	}
+}
+
+/// A set of variants of an image in a single format.
+#[derive(Debug, Serialize)]
+pub struct ImageSource {
+	/// The MIME type of the variants.
+	pub mime_type: &'static str,
+	/// The `srcset` attribute listing every variant.
+	pub srcset: String,
+	/// URL to the largest variant.
+	pub largest: String,
+}
+
+/// An image processed by the image pipeline.
+#[derive(Debug, Serialize)]
+pub struct ProcessedImage {
+	/// The image's variants in each format, in order of preference.
+	pub sources: Vec<ImageSource>,
+}
+
+/// Whether the file at the given path is an image webdog can read.
+pub(crate) fn is_image(path: &Path) -> bool {
+	path.extension()
+		.and_then(|ext| ext.to_str())
+		.is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Finds every image in the given directory, relative to it.
+fn find_images(dir: &Path) -> eyre::Result<Vec<PathBuf>> {
+	let mut paths = Vec::new();
+	if !dir.exists() {
+		return Ok(paths);
+	}
+	for entry in walkdir::WalkDir::new(dir) {
+		let entry = entry?;
+		if entry.file_type().is_file() && is_image(entry.path()) {
+			paths.push(entry.path().strip_prefix(dir)?.to_owned());
+		}
+	}
+	Ok(paths)
+}
+
+/// Reads the dimensions of every image in the site's root directory and CDN mirror.
+///
+/// Returns the dimensions by the URL the images are served at, which is a path for images in the root directory and a full URL for images on the CDN.
+pub(crate) fn read_dimensions(builder: &SiteBuilder) -> eyre::Result<BTreeMap<String, (u32, u32)>> {
+	let config = &builder.site.config;
+	let root_path = builder.site.site_path.join(ROOT_PATH);
+	let mut images = Vec::new();
+	for path in find_images(&root_path)? {
+		images.push((
+			root_path.join(&path),
+			format!("/{}", util::template_name(&path)),
+		));
+	}
+	if let Some(cdn_mirror) = &config.cdn_mirror {
+		let mirror_path = builder.site.site_path.join(cdn_mirror);
+		for path in find_images(&mirror_path)? {
+			images.push((
+				mirror_path.join(&path),
+				config.cdn_url(&util::template_name(&path))?.to_string(),
+			));
+		}
+	}
+
+	Ok(images
+		.into_par_iter()
+		.filter_map(|(path, url)| {
+			// only the image's header is read, so this is quick even for large images
+			match image::image_dimensions(&path) {
+				Ok(dimensions) => Some((url, dimensions)),
+				Err(e) => {
+					// the image's tags are left as they are rather than failing the whole build
+					eprintln!(
+						"Failed to read dimensions of image at {}, skipping: {e}",
+						path.display()
+					);
+					None
+				}
+			}
+		})
+		.collect())
+}
+
+/// Processes every image in the site's root directory, if enabled in the site config.
+///
+/// Returns the processed images by the URL path they're served at.
+///
+/// Images whose dimensions couldn't be read by [`read_dimensions`] are skipped, so this must be called after it.
+pub(crate) fn process_images(
+	builder: &SiteBuilder,
+) -> eyre::Result<BTreeMap<String, ProcessedImage>> {
+	let Some(config) = &builder.site.config.images else {
+		return Ok(BTreeMap::new());
+	};
+	let root_path = builder.site.site_path.join(ROOT_PATH);
+	find_images(&root_path)?
+		.par_iter()
+		.map(|path| (path, format!("/{}", util::template_name(path))))
+		.filter(|(_, url)| builder.image_dimensions.contains_key(url))
+		.map(|(path, url)| {
+			let image = process_image(builder, config, &root_path, path)
+				.with_context(|| format!("Failed to process image at {}", path.display()))?;
+			Ok((url, image))
+		})
+		.collect()
+}
+
+/// Builds the variants of a single image, relative to the root directory.
+fn process_image(
+	builder: &SiteBuilder,
+	config: &ImageConfig,
+	root_path: &Path,
+	path: &Path,
+) -> eyre::Result<ProcessedImage> {
+	let source_path = root_path.join(path);
+	let source_format = path
+		.extension()
+		.and_then(|ext| ext.to_str())
+		.and_then(ImageFormat::from_extension)
+		.ok_or_else(|| eyre::eyre!("unsupported image format"))?;
+	let contents = std::fs::read(&source_path)?;
+	// variants are named by what they're encoded from, so changing the quality gives them new names too
+	let hash = CacheKey::new()
+		.add(&contents)
+		.add([config.quality])
+		.finish();
+	let hash = &hash[..16];
+	let (width, _) = image::ImageReader::new(Cursor::new(&contents))
+		.with_guessed_format()?
+		.into_dimensions()?;
+
+	let mut widths: Vec<_> = config
+		.widths
+		.iter()
+		.copied()
+		.filter(|w| *w < width)
+		.chain([width])
+		.collect();
+	widths.sort();
+	widths.dedup();
+
+	let stem = path
+		.file_stem()
+		.and_then(|stem| stem.to_str())
+		.ok_or_else(|| eyre::eyre!("invalid image filename"))?;
+	let out_dir = PathBuf::from(&config.output_path).join(path.parent().unwrap_or(Path::new("")));
+
+	// the source is only decoded if a variant actually needs to be built
+	let mut decoded = None;
+	let mut sources = Vec::new();
+	let mut formats = Vec::new();
+	for format in &config.formats {
+		let format = format.resolve(source_format);
+		if !formats.contains(&format) {
+			formats.push(format);
+		}
+	}
+	for format in formats {
+		let mut srcset = Vec::new();
+		let mut largest = String::new();
+		for &variant_width in &widths {
+			let variant_path = out_dir.join(format!(
+				"{stem}-{hash}-{variant_width}.{}",
+				format.extension()
+			));
+			let key = CacheKey::new()
+				.add(hash)
+				.add(variant_width.to_le_bytes())
+				.add(format.extension())
+				.add([config.quality]);
+			let out_path = builder.build_path.join(&variant_path);
+			// variants are kept when the rest of the build directory is cleared, so existing ones don't need encoding again
+			if out_path.exists() {
+				builder.cache.record(&variant_path, &key.finish());
+			} else {
+				builder.write_output(&out_path, key, || {
+					let image: &DynamicImage = match &mut decoded {
+						Some(image) => image,
+						None => decoded.insert(image::load_from_memory(&contents)?),
+					};
+					if variant_width == width {
+						format.encode(image, config.quality)
+					} else {
+						format.encode(
+							&image.resize(variant_width, u32::MAX, FilterType::Lanczos3),
+							config.quality,
+						)
+					}
+				})?;
+			}
+			largest = format!("/{}", util::template_name(&variant_path));
+			srcset.push(format!("{largest} {variant_width}w"));
+		}
+		sources.push(ImageSource {
+			mime_type: format.mime_type(),
+			srcset: srcset.join(", "),
+			largest,
+		});
+	}
+
+	Ok(ProcessedImage { sources })
+}
@@ -1,20 +1,39 @@
+mod assets;
 mod builder;
+mod cache;
+mod check;
+pub mod code;
+mod dependencies;
 mod extras;
+mod feed;
 pub mod frontmatter;
+pub mod i18n;
+pub mod images;
 mod link_list;
+pub mod markdown;
+mod math;
 pub mod resource;
+pub mod sass;
+mod search;
 #[cfg(feature = "serve")]
 pub mod serving;
+mod sitemap;
 mod util;
 
 use std::{
-	collections::HashMap,
+	collections::{HashMap, HashSet},
 	path::{Path, PathBuf},
 };
 
+use assets::FingerprintConfig;
+use code::CodeStyle;
 use extras::ExtraData;
 use eyre::Context;
+use i18n::{I18nConfig, PageLanguage, Translation};
+use images::ImageConfig;
+use markdown::MarkdownConfig;
 use resource::{EmbedMetadata, ResourceBuilderConfig};
+use sass::{SassConfig, SassSheet};
 use serde::{Deserialize, Serialize};
 use url::Url;
 use walkdir::WalkDir;
@@ -31,6 +50,10 @@ pub const SASS_PATH: &str = "sass";
 pub const ROOT_PATH: &str = "root";
 /// Source base path for resources.
 pub const RESOURCES_PATH: &str = "resources";
+/// Source base path for custom syntax definitions.
+pub const SYNTAXES_PATH: &str = "syntaxes";
+/// Source base path for custom code themes.
+pub const THEMES_PATH: &str = "themes";
 
 /// Struct for the site's configuration.
 #[derive(Debug, Serialize, Deserialize)]
@@ -46,16 +69,46 @@ pub struct SiteConfig {
 	/// The site's build directory. Defaults to <site>/build if not specified.
 	pub build: Option<String>,
 	/// A list of Sass stylesheets that will be built.
-	pub sass_styles: Vec<PathBuf>,
+	pub sass_styles: Vec<SassSheet>,
+	/// Options for building the site's Sass stylesheets.
+	#[serde(default)]
+	pub sass: SassConfig,
 	/// URL to the CDN used for the site's images.
 	pub cdn_url: Url,
+	/// Path to a local copy of the CDN's files, relative to the site, used to read the dimensions of CDN images.
+	#[serde(default)]
+	pub cdn_mirror: Option<PathBuf>,
 	/// The path to output webdog static resources to. Defaults to "webdog"
 	pub webdog_path: Option<String>,
-	/// The theme to use for the site's code blocks.
-	/// TODO: dark/light themes
-	/// TODO: export themes as CSS instead of styling HTML directly
-	/// TODO: allow loading user themes
+	/// The theme to use for the site's code blocks. Used as the light theme if a dark theme is set.
 	pub code_theme: String,
+	/// The theme to use for the site's code blocks in dark mode. Only used with class-based highlighting.
+	#[serde(default)]
+	pub code_theme_dark: Option<String>,
+	/// How the site's code blocks are highlighted.
+	#[serde(default)]
+	pub code_style: CodeStyle,
+	/// Whether builds should fail if the site contains broken internal links.
+	#[serde(default)]
+	pub check_links: bool,
+	/// Whether to build a search index and search script for the site.
+	#[serde(default)]
+	pub search_index: bool,
+	/// Whether to add anchor links to headings.
+	#[serde(default)]
+	pub heading_anchors: bool,
+	/// The Markdown extensions to enable.
+	#[serde(default)]
+	pub markdown: MarkdownConfig,
+	/// Config for building responsive variants of the site's images, if enabled.
+	#[serde(default)]
+	pub images: Option<ImageConfig>,
+	/// Config for giving the site's assets content-hashed filenames, if enabled.
+	#[serde(default)]
+	pub fingerprint: Option<FingerprintConfig>,
+	/// Config for the site's languages, if the site is multilingual.
+	#[serde(default)]
+	pub i18n: Option<I18nConfig>,
 
 	/// List of resources the site should build.
 	pub resources: HashMap<String, ResourceBuilderConfig>,
@@ -73,10 +126,21 @@ impl SiteConfig {
 			description: Default::default(),
 			theme_color: "#ffc4fc".to_string(),
 			build: None,
-			sass_styles: vec!["index.scss".into()],
+			sass_styles: vec![SassSheet::Path("index.scss".into())],
+			sass: Default::default(),
 			cdn_url,
+			cdn_mirror: None,
 			webdog_path: None,
 			code_theme: "base16-ocean.dark".to_string(),
+			code_theme_dark: None,
+			code_style: CodeStyle::default(),
+			check_links: false,
+			search_index: false,
+			heading_anchors: false,
+			markdown: Default::default(),
+			images: None,
+			fingerprint: None,
+			i18n: None,
 			resources: Default::default(),
 		}
 	}
@@ -86,14 +150,45 @@ impl SiteConfig {
 		Ok(self.cdn_url.join(file)?)
 	}
 
+	/// Gets the path prefix for pages and resources in the given language, with a trailing slash unless it's empty.
+	pub fn language_prefix(&self, language: Option<&str>) -> String {
+		match (&self.i18n, language) {
+			(Some(i18n), Some(language)) => i18n.prefix(language),
+			_ => String::new(),
+		}
+	}
+
 	/// Checks the site config for errors.
 	pub fn check(&self, builder: &SiteBuilder) -> eyre::Result<()> {
-		builder
-			.theme_set
-			.themes
-			.contains_key(&self.code_theme)
-			.then_some(())
-			.ok_or_else(|| eyre::eyre!("missing code theme: {}", self.code_theme))?;
+		for theme in std::iter::once(&self.code_theme).chain(&self.code_theme_dark) {
+			builder
+				.theme_set
+				.themes
+				.contains_key(theme)
+				.then_some(())
+				.ok_or_else(|| eyre::eyre!("missing code theme: {theme}"))?;
+		}
+		let mut sass_outputs = HashSet::new();
+		for sheet in &self.sass_styles {
+			let output = sheet.output_path();
+			if !sass_outputs.insert(output.clone()) {
+				eyre::bail!(
+					"multiple Sass stylesheets are built to styles/{}",
+					output.display()
+				);
+			}
+		}
+		if let Some(i18n) = &self.i18n {
+			i18n.check()?;
+		}
+		if let Some(images) = &self.images {
+			if images.formats.is_empty() {
+				eyre::bail!("at least one image format must be set");
+			}
+			if !(1..=100).contains(&images.quality) {
+				eyre::bail!("image quality must be between 1 and 100");
+			}
+		}
 		Ok(())
 	}
 
@@ -135,9 +230,120 @@ pub struct PageMetadata {
 	/// Custom values passed to the base template.
 	#[serde(default)]
 	pub userdata: serde_yaml_ng::Value,
+	/// Whether to leave the page out of the site's sitemap.
+	#[serde(default)]
+	pub exclude_from_sitemap: bool,
+	/// Whether to leave the page out of the site's search index.
+	#[serde(default)]
+	pub exclude_from_search: bool,
+	/// Overrides for the site's Markdown extensions, if any.
+	#[serde(default)]
+	pub markdown: Option<serde_yaml_ng::Mapping>,
 	/// Whether this page being rendered is a partial. Set by the builder, not your page metadata.
 	#[serde(skip)]
 	pub is_partial: bool,
+	/// Alternate versions of the page, such as feeds. Set by the builder, not your page metadata.
+	#[serde(skip)]
+	pub alternates: Vec<AlternateLink>,
+	/// The page's table of contents. Set by the builder, not your page metadata.
+	#[serde(skip)]
+	pub toc: Vec<TocEntry>,
+	/// The page's language, if the site is multilingual. Set by the builder, not your page metadata.
+	#[serde(skip)]
+	pub language: Option<String>,
+	/// Every translation of the page, including itself. Set by the builder, not your page metadata.
+	#[serde(skip)]
+	pub translations: Vec<Translation>,
+}
+
+/// Struct for alternate versions of a page, linked to with `<link rel="alternate">` tags.
+#[derive(Debug, Clone, Serialize)]
+pub struct AlternateLink {
+	/// The alternate's URL.
+	pub href: String,
+	/// The alternate's MIME type, if any.
+	#[serde(rename = "type")]
+	pub kind: Option<String>,
+	/// The alternate's title, if any.
+	pub title: Option<String>,
+	/// The alternate's language, if it's a translation of the page.
+	pub hreflang: Option<String>,
+}
+
+impl AlternateLink {
+	/// Creates a new alternate link for a feed.
+	pub fn feed(href: String, kind: &str, title: &str) -> Self {
+		Self {
+			href,
+			kind: Some(kind.to_string()),
+			title: Some(title.to_string()),
+			hreflang: None,
+		}
+	}
+
+	/// Creates a new alternate link for a translation of the page.
+	pub fn translation(href: String, language: &str) -> Self {
+		Self {
+			href,
+			kind: None,
+			title: None,
+			hreflang: Some(language.to_string()),
+		}
+	}
+
+	/// Builds the alternate's link tag.
+	pub fn build(&self) -> String {
+		let mut s = format!(
+			r#"<link rel="alternate" href="{}""#,
+			util::escape_attribute(&self.href)
+		);
+		if let Some(kind) = &self.kind {
+			s = format!(r#"{s} type="{}""#, util::escape_attribute(kind));
+		}
+		if let Some(title) = &self.title {
+			s = format!(r#"{s} title="{}""#, util::escape_attribute(title));
+		}
+		if let Some(hreflang) = &self.hreflang {
+			s = format!(r#"{s} hreflang="{}""#, util::escape_attribute(hreflang));
+		}
+		s.push('>');
+		s
+	}
+}
+
+/// Struct for a heading in a page's table of contents.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TocEntry {
+	/// The heading's level, from 1 to 6.
+	pub level: usize,
+	/// The heading's ID, for linking to it.
+	pub id: String,
+	/// The heading's text.
+	pub title: String,
+	/// The headings nested under this one.
+	pub children: Vec<TocEntry>,
+}
+
+impl TocEntry {
+	/// Builds a table of contents tree from a flat list of headings' levels, IDs, and titles.
+	pub fn tree(headings: Vec<(usize, String, String)>) -> Vec<Self> {
+		let mut tree: Vec<Self> = Vec::new();
+		for (level, id, title) in headings {
+			let entry = Self {
+				level,
+				id,
+				title,
+				children: Vec::new(),
+			};
+			// descend into the last entry while it's a higher level heading
+			let mut siblings = &mut tree;
+			while siblings.last().is_some_and(|last| last.level < level) {
+				siblings = &mut siblings.last_mut().expect("checked above").children;
+			}
+			siblings.push(entry);
+		}
+		tree
+	}
 }
 
 /// Struct containing information about the site.
@@ -149,6 +355,8 @@ pub struct Site {
 	pub config: SiteConfig,
 	/// An index of available pages.
 	pub page_index: HashMap<String, PathBuf>,
+	/// The language of each page in the index, if the site is multilingual.
+	pub page_languages: HashMap<String, PageLanguage>,
 }
 
 impl Site {
@@ -156,8 +364,22 @@ impl Site {
 	pub fn new(site_path: &Path) -> eyre::Result<Self> {
 		let config = SiteConfig::read(site_path)?;
 
-		let mut page_index = HashMap::new();
-		let pages_path = site_path.join(PAGES_PATH);
+		let mut site = Self {
+			site_path: site_path.to_owned(),
+			config,
+			page_index: HashMap::new(),
+			page_languages: HashMap::new(),
+		};
+		site.reindex_pages()?;
+
+		Ok(site)
+	}
+
+	/// Indexes every page in the site again, i.e. after the site's languages changed.
+	pub fn reindex_pages(&mut self) -> eyre::Result<()> {
+		self.page_index.clear();
+		self.page_languages.clear();
+		let pages_path = self.site_path.join(PAGES_PATH);
 		for entry in WalkDir::new(&pages_path).into_iter() {
 			let entry = entry.wrap_err("Failed to read page entry")?;
 			let path = entry.path();
@@ -166,26 +388,126 @@ impl Site {
 				&& ext == "md"
 				&& entry.file_type().is_file()
 			{
-				page_index.insert(
-					path.strip_prefix(&pages_path)
-						.wrap_err("This really shouldn't have happened")?
-						.with_extension("")
-						.to_string_lossy()
-						.to_string(),
-					path.to_owned(),
-				);
+				self.index_page(path)?;
 			}
 		}
 
-		Ok(Self {
-			site_path: site_path.to_owned(),
-			config,
-			page_index,
-		})
+		Ok(())
+	}
+
+	/// Adds a page to the page index, returning its name.
+	///
+	/// Pages in a language other than the default one are named with their language's prefix, i.e. `about.fr.md` is named `fr/about`.
+	pub fn index_page(&mut self, path: &Path) -> eyre::Result<String> {
+		let name = path
+			.strip_prefix(self.site_path.join(PAGES_PATH))
+			.wrap_err("This really shouldn't have happened")?
+			.with_extension("")
+			.to_string_lossy()
+			.replace(std::path::MAIN_SEPARATOR, "/");
+		let Some(i18n) = &self.config.i18n else {
+			self.page_index.insert(name.clone(), path.to_owned());
+			return Ok(name);
+		};
+		let (base, language) = i18n.split_language(&name);
+		let page_name = format!("{}{base}", i18n.prefix(language));
+		if let Some(existing) = self.page_index.get(&page_name)
+			&& existing != path
+		{
+			eyre::bail!(
+				"{} and {} are both built to the page {page_name}",
+				existing.display(),
+				path.display()
+			);
+		}
+		self.page_languages.insert(
+			page_name.clone(),
+			PageLanguage {
+				language: language.to_string(),
+				base: base.to_string(),
+			},
+		);
+		self.page_index.insert(page_name.clone(), path.to_owned());
+		Ok(page_name)
+	}
+
+	/// Gets the name of the indexed page at the given path.
+	pub fn page_name(&self, path: &Path) -> Option<String> {
+		self.page_index
+			.iter()
+			.find(|(_, page_path)| *page_path == path)
+			.map(|(page_name, _)| page_name.clone())
+	}
+
+	/// Removes a page from the page index, returning its name if it was indexed.
+	pub fn unindex_page(&mut self, path: &Path) -> Option<String> {
+		let page_name = self.page_name(path)?;
+		self.page_index.remove(&page_name);
+		self.page_languages.remove(&page_name);
+		Some(page_name)
+	}
+
+	/// Gets every translation of a page, including itself, sorted by language.
+	///
+	/// Returns nothing if the page hasn't been translated.
+	pub fn page_translations(&self, page_name: &str) -> Vec<(String, Translation)> {
+		let (Some(i18n), Some(page_language)) =
+			(&self.config.i18n, self.page_languages.get(page_name))
+		else {
+			return Vec::new();
+		};
+		let mut translations: Vec<_> = self
+			.page_languages
+			.iter()
+			.filter(|(_, other)| other.base == page_language.base)
+			.map(|(name, other)| {
+				(
+					name.clone(),
+					Translation {
+						code: other.language.clone(),
+						name: i18n
+							.languages
+							.get(&other.language)
+							.map(|l| l.name.clone())
+							.unwrap_or_default(),
+						url: util::page_url(name),
+					},
+				)
+			})
+			.collect();
+		if translations.len() < 2 {
+			return Vec::new();
+		}
+		translations.sort_by(|(_, a), (_, b)| a.code.cmp(&b.code));
+		translations
+	}
+
+	/// Builds the site once. If `clean` is set, the build cache is discarded and the whole site is rebuilt.
+	pub fn build_once(self, clean: bool) -> eyre::Result<()> {
+		if clean {
+			let cache_path = self.site_path.join(cache::CACHE_FILENAME);
+			if cache_path.exists() {
+				std::fs::remove_file(cache_path)?;
+			}
+		}
+		let builder = SiteBuilder::new(self, false)?;
+		if clean && builder.build_path.exists() {
+			// nothing is kept from previous builds, including image variants
+			util::remove_dir_contents(&builder.build_path)
+				.wrap_err("Failed to remove old contents of build directory")?;
+		}
+		let builder = builder.prepare()?;
+		builder.build_all()?;
+		if builder.site.config.check_links {
+			check::report(&check::check_links(&builder)?)?;
+		}
+		Ok(())
 	}
 
-	/// Builds the site once.
-	pub fn build_once(self) -> eyre::Result<()> {
-		SiteBuilder::new(self, false)?.prepare()?.build_all()
+	/// Builds the site once and checks the output for broken internal links.
+	pub fn check(self) -> eyre::Result<()> {
+		let builder = SiteBuilder::new(self, false)?.prepare()?;
+		builder.build_all()?;
+		check::report(&check::check_links(&builder)?)
 	}
 }
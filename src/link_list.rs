@@ -28,11 +28,13 @@ pub fn render_basic_link_list(
 	template: &str,
 	links: Vec<Link>,
 	title: &str,
+	language: Option<&str>,
 ) -> eyre::Result<String> {
 	builder.build_page_raw(
 		PageMetadata {
 			template: Some(template.to_owned()),
 			title: Some(title.to_owned()),
+			language: language.map(str::to_owned),
 			..Default::default()
 		},
 		"",
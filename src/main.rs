@@ -40,7 +40,13 @@ enum Commands {
 		cdn_url: Option<Url>,
 	},
 	/// Builds the site.
-	Build {},
+	Build {
+		/// Whether to ignore the build cache and rebuild the entire site.
+		#[arg(long, default_value = "false")]
+		clean: bool,
+	},
+	/// Builds the site and checks it for broken internal links.
+	Check,
 	/// Serves the site for locally viewing edits made before publishing.
 	#[cfg(feature = "serve")]
 	Serve {
@@ -147,13 +153,19 @@ fn main() -> eyre::Result<()> {
 
 			Ok(())
 		}
-		Commands::Build {} => {
+		Commands::Build { clean } => {
 			println!("Building site...");
 			let now = std::time::Instant::now();
-			site()?.build_once()?;
+			site()?.build_once(clean)?;
 			println!("Build completed in {:?}", now.elapsed());
 			Ok(())
 		}
+		Commands::Check => {
+			println!("Checking site...");
+			site()?.check()?;
+			println!("No broken links found!");
+			Ok(())
+		}
 		#[cfg(feature = "serve")]
 		Commands::Serve { ip, port } => {
 			let site = site()?;
@@ -207,7 +219,7 @@ fn main() -> eyre::Result<()> {
 				let rss = (!no_rss).then(|| ResourceRSSBuilderConfig {
 					template: format!("{id}/rss.tera"),
 					title: id.clone(),
-					description: Default::default(),
+					..Default::default()
 				});
 
 				let resource_config = ResourceBuilderConfig {
@@ -218,11 +230,14 @@ fn main() -> eyre::Result<()> {
 					resource_list_template: format!("{id}/list.tera"),
 					tag_list_template: "basic-link-list.tera".to_string(),
 					rss,
+					archive: None,
 					list_title: name.clone(),
 					tag_list_title: format!("{name} tags"),
+					series_list_title: None,
 					resource_name_plural: plural,
 					resources_per_page: 3,
 					timestamp_format: "[weekday], [month repr:long] [day], [year]".to_string(),
+					related_count: ResourceBuilderConfig::default_related_count(),
 				};
 
 				config.resources.insert(id.clone(), resource_config);
@@ -242,6 +257,11 @@ fn main() -> eyre::Result<()> {
 						desc: Some(format!("This is the first {name} :)")),
 						inner: serde_yaml_ng::Value::Null,
 						draft: true,
+						markdown: None,
+						language: None,
+						series: None,
+						series_part: None,
+						toc: Vec::new(),
 					},
 				)?;
 
@@ -309,6 +329,7 @@ fn main() -> eyre::Result<()> {
 					return Ok(());
 				}
 
+				std::fs::create_dir_all(resource_path.parent().expect("should never fail"))?;
 				create_resource(
 					&resource_path,
 					&ResourceMetadata {
@@ -319,6 +340,11 @@ fn main() -> eyre::Result<()> {
 						desc: description,
 						inner: serde_yaml_ng::Value::Null,
 						draft: !skip_draft,
+						markdown: None,
+						language: None,
+						series: None,
+						series_part: None,
+						toc: Vec::new(),
 					},
 				)?;
 
@@ -0,0 +1,112 @@
+//! Module containing the config for the Markdown extensions used to render pages and resources.
+
+use eyre::Context;
+use pulldown_cmark::Options;
+use serde::{Deserialize, Serialize};
+
+/// The Markdown extensions to enable. Every extension is enabled by default.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MarkdownConfig {
+	/// GitHub-style tables.
+	pub tables: bool,
+	/// Footnotes, i.e. `[^1]`.
+	pub footnotes: bool,
+	/// The older, non-GitHub footnote syntax, where a definition continues onto unindented lines. Only applies when footnotes are enabled.
+	pub old_footnotes: bool,
+	/// Strikethrough, i.e. `~~text~~`.
+	pub strikethrough: bool,
+	/// Task lists, i.e. `- [x] done`.
+	pub tasklists: bool,
+	/// Replacing quotes, dashes, and ellipses with their typographic versions.
+	pub smart_punctuation: bool,
+	/// Custom IDs and classes on headings, i.e. `# heading {#id .class}`.
+	pub heading_attributes: bool,
+	/// Math, i.e. `$x^2$` and `$$x^2$$`.
+	pub math: bool,
+	/// GitHub-style blockquote alerts, i.e. `> [!NOTE]`.
+	pub alerts: bool,
+	/// Definition lists.
+	pub definition_lists: bool,
+	/// Superscript, i.e. `^text^`.
+	pub superscript: bool,
+	/// Subscript, i.e. `~text~`.
+	pub subscript: bool,
+	/// Wikilinks, i.e. `[[page]]`.
+	pub wikilinks: bool,
+	/// YAML-style metadata blocks delimited by `---`, which are left out of the rendered HTML.
+	pub yaml_metadata_blocks: bool,
+	/// Metadata blocks delimited by `+++`, which are left out of the rendered HTML.
+	pub pluses_metadata_blocks: bool,
+}
+
+impl Default for MarkdownConfig {
+	fn default() -> Self {
+		Self {
+			tables: true,
+			footnotes: true,
+			old_footnotes: true,
+			strikethrough: true,
+			tasklists: true,
+			smart_punctuation: true,
+			heading_attributes: true,
+			math: true,
+			alerts: true,
+			definition_lists: true,
+			superscript: true,
+			subscript: true,
+			wikilinks: true,
+			yaml_metadata_blocks: true,
+			pluses_metadata_blocks: true,
+		}
+	}
+}
+
+impl MarkdownConfig {
+	/// Applies a page's overrides on top of the config.
+	pub fn with_overrides(&self, overrides: Option<&serde_yaml_ng::Mapping>) -> eyre::Result<Self> {
+		let Some(overrides) = overrides else {
+			return Ok(self.clone());
+		};
+		let serde_yaml_ng::Value::Mapping(mut config) = serde_yaml_ng::to_value(self)? else {
+			unreachable!("markdown config should always serialize to a mapping");
+		};
+		config.extend(overrides.clone());
+		serde_yaml_ng::from_value(serde_yaml_ng::Value::Mapping(config))
+			.wrap_err("Invalid Markdown extension overrides")
+	}
+
+	/// Gets the parser options for the enabled extensions.
+	pub fn options(&self) -> Options {
+		let mut options = Options::empty();
+		for (enabled, option) in [
+			(self.tables, Options::ENABLE_TABLES),
+			(self.footnotes, Options::ENABLE_FOOTNOTES),
+			(self.strikethrough, Options::ENABLE_STRIKETHROUGH),
+			(self.tasklists, Options::ENABLE_TASKLISTS),
+			(self.smart_punctuation, Options::ENABLE_SMART_PUNCTUATION),
+			(self.heading_attributes, Options::ENABLE_HEADING_ATTRIBUTES),
+			(self.math, Options::ENABLE_MATH),
+			(self.alerts, Options::ENABLE_GFM),
+			(self.definition_lists, Options::ENABLE_DEFINITION_LIST),
+			(self.superscript, Options::ENABLE_SUPERSCRIPT),
+			(self.subscript, Options::ENABLE_SUBSCRIPT),
+			(self.wikilinks, Options::ENABLE_WIKILINKS),
+			(
+				self.yaml_metadata_blocks,
+				Options::ENABLE_YAML_STYLE_METADATA_BLOCKS,
+			),
+			(
+				self.pluses_metadata_blocks,
+				Options::ENABLE_PLUSES_DELIMITED_METADATA_BLOCKS,
+			),
+		] {
+			options.set(option, enabled);
+		}
+		// the old footnotes flag includes the footnotes one, so it's only added on top of it
+		if self.footnotes && self.old_footnotes {
+			options.insert(Options::ENABLE_OLD_FOOTNOTES);
+		}
+		options
+	}
+}
@@ -0,0 +1,906 @@
+//! Module containing a small TeX to MathML converter, used to render math at build time.
+//!
+//! Only the commonly used subset of TeX math is supported. Unknown commands are rendered as errors in the output rather than failing the build.
+
+use crate::util::escape_attribute;
+
+/// A token in some TeX math.
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+	/// A command, i.e. `\frac` or `\{`, without its backslash.
+	Command(String),
+	/// A number, including any decimal point.
+	Number(String),
+	/// Any other single character.
+	Char(char),
+	/// Whitespace, which is only meaningful in text.
+	Space,
+	/// The start of a group, `{`.
+	Open,
+	/// The end of a group, `}`.
+	Close,
+	/// A superscript, `^`.
+	Sup,
+	/// A subscript, `_`.
+	Sub,
+	/// A column separator in environments, `&`.
+	Align,
+}
+
+/// Splits TeX math into tokens.
+fn tokenize(tex: &str) -> Vec<Token> {
+	let mut tokens = Vec::new();
+	let mut chars = tex.chars().peekable();
+	while let Some(c) = chars.next() {
+		let token = match c {
+			'\\' => match chars.next() {
+				Some(c) if c.is_ascii_alphabetic() => {
+					let mut name = c.to_string();
+					while let Some(c) = chars.next_if(|c| c.is_ascii_alphabetic()) {
+						name.push(c);
+					}
+					Token::Command(name)
+				}
+				Some(c) if c.is_whitespace() => Token::Command(" ".to_string()),
+				Some(c) => Token::Command(c.to_string()),
+				None => Token::Char('\\'),
+			},
+			c if c.is_ascii_digit() => {
+				let mut number = c.to_string();
+				while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+					number.push(c);
+				}
+				Token::Number(number)
+			}
+			c if c.is_whitespace() => Token::Space,
+			'{' => Token::Open,
+			'}' => Token::Close,
+			'^' => Token::Sup,
+			'_' => Token::Sub,
+			'&' => Token::Align,
+			c => Token::Char(c),
+		};
+		tokens.push(token);
+	}
+	tokens
+}
+
+/// Gets the identifier for a command, i.e. a Greek letter.
+fn identifier(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"alpha" => "α",
+		"beta" => "β",
+		"gamma" => "γ",
+		"delta" => "δ",
+		"epsilon" => "ϵ",
+		"varepsilon" => "ε",
+		"zeta" => "ζ",
+		"eta" => "η",
+		"theta" => "θ",
+		"vartheta" => "ϑ",
+		"iota" => "ι",
+		"kappa" => "κ",
+		"lambda" => "λ",
+		"mu" => "μ",
+		"nu" => "ν",
+		"xi" => "ξ",
+		"pi" => "π",
+		"varpi" => "ϖ",
+		"rho" => "ρ",
+		"varrho" => "ϱ",
+		"sigma" => "σ",
+		"varsigma" => "ς",
+		"tau" => "τ",
+		"upsilon" => "υ",
+		"phi" => "ϕ",
+		"varphi" => "φ",
+		"chi" => "χ",
+		"psi" => "ψ",
+		"omega" => "ω",
+		"Gamma" => "Γ",
+		"Delta" => "Δ",
+		"Theta" => "Θ",
+		"Lambda" => "Λ",
+		"Xi" => "Ξ",
+		"Pi" => "Π",
+		"Sigma" => "Σ",
+		"Upsilon" => "Υ",
+		"Phi" => "Φ",
+		"Psi" => "Ψ",
+		"Omega" => "Ω",
+		"infty" => "∞",
+		"partial" => "∂",
+		"nabla" => "∇",
+		"ell" => "ℓ",
+		"hbar" => "ℏ",
+		"emptyset" => "∅",
+		"varnothing" => "∅",
+		"Re" => "ℜ",
+		"Im" => "ℑ",
+		"aleph" => "ℵ",
+		_ => return None,
+	})
+}
+
+/// Gets the operator for a command, i.e. a relation or arrow.
+fn operator(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"pm" => "±",
+		"mp" => "∓",
+		"times" => "×",
+		"div" => "÷",
+		"cdot" => "⋅",
+		"ast" => "∗",
+		"star" => "⋆",
+		"circ" => "∘",
+		"bullet" => "∙",
+		"oplus" => "⊕",
+		"otimes" => "⊗",
+		"leq" | "le" => "≤",
+		"geq" | "ge" => "≥",
+		"neq" | "ne" => "≠",
+		"approx" => "≈",
+		"equiv" => "≡",
+		"sim" => "∼",
+		"simeq" => "≃",
+		"cong" => "≅",
+		"propto" => "∝",
+		"ll" => "≪",
+		"gg" => "≫",
+		"in" => "∈",
+		"notin" => "∉",
+		"ni" => "∋",
+		"subset" => "⊂",
+		"supset" => "⊃",
+		"subseteq" => "⊆",
+		"supseteq" => "⊇",
+		"cup" => "∪",
+		"cap" => "∩",
+		"setminus" => "∖",
+		"wedge" | "land" => "∧",
+		"vee" | "lor" => "∨",
+		"neg" | "lnot" => "¬",
+		"forall" => "∀",
+		"exists" => "∃",
+		"to" | "rightarrow" => "→",
+		"leftarrow" | "gets" => "←",
+		"leftrightarrow" => "↔",
+		"Rightarrow" => "⇒",
+		"Leftarrow" => "⇐",
+		"Leftrightarrow" => "⇔",
+		"implies" => "⟹",
+		"iff" => "⟺",
+		"mapsto" => "↦",
+		"uparrow" => "↑",
+		"downarrow" => "↓",
+		"ldots" | "dots" => "…",
+		"cdots" => "⋯",
+		"vdots" => "⋮",
+		"ddots" => "⋱",
+		"langle" => "⟨",
+		"rangle" => "⟩",
+		"lfloor" => "⌊",
+		"rfloor" => "⌋",
+		"lceil" => "⌈",
+		"rceil" => "⌉",
+		"mid" => "∣",
+		"parallel" => "∥",
+		"perp" => "⊥",
+		"angle" => "∠",
+		"triangle" => "△",
+		"prime" => "′",
+		"colon" => ":",
+		"{" => "{",
+		"}" => "}",
+		"|" => "‖",
+		"%" => "%",
+		"$" => "$",
+		"&" => "&",
+		"#" => "#",
+		"_" => "_",
+		_ => return None,
+	})
+}
+
+/// Gets a large operator for a command, along with whether its scripts are placed above and below it.
+fn large_operator(name: &str) -> Option<(&'static str, bool)> {
+	Some(match name {
+		"sum" => ("∑", true),
+		"prod" => ("∏", true),
+		"coprod" => ("∐", true),
+		"bigcup" => ("⋃", true),
+		"bigcap" => ("⋂", true),
+		"bigoplus" => ("⨁", true),
+		"bigotimes" => ("⨂", true),
+		"int" => ("∫", false),
+		"iint" => ("∬", false),
+		"iiint" => ("∭", false),
+		"oint" => ("∮", false),
+		_ => return None,
+	})
+}
+
+/// Whether a command is a named function, along with whether its scripts are placed below it.
+fn function(name: &str) -> Option<bool> {
+	match name {
+		"sin" | "cos" | "tan" | "cot" | "sec" | "csc" | "arcsin" | "arccos" | "arctan" | "sinh"
+		| "cosh" | "tanh" | "log" | "ln" | "lg" | "exp" | "deg" | "dim" | "ker" | "hom" | "arg"
+		| "Pr" => Some(false),
+		"lim" | "liminf" | "limsup" | "max" | "min" | "sup" | "inf" | "det" | "gcd" => Some(true),
+		_ => None,
+	}
+}
+
+/// Gets the accent placed over or under an argument for a command, along with whether it goes under.
+fn accent(name: &str) -> Option<(&'static str, bool)> {
+	Some(match name {
+		"hat" | "widehat" => ("^", false),
+		"bar" | "overline" => ("‾", false),
+		"vec" | "overrightarrow" => ("→", false),
+		"overleftarrow" => ("←", false),
+		"tilde" | "widetilde" => ("~", false),
+		"dot" => ("˙", false),
+		"ddot" => ("¨", false),
+		"overbrace" => ("⏞", false),
+		"underline" => ("_", true),
+		"underbrace" => ("⏟", true),
+		_ => return None,
+	})
+}
+
+/// Gets the width of a spacing command.
+fn space(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"," => "0.1667em",
+		":" | ">" => "0.2222em",
+		";" => "0.2778em",
+		" " => "0.25em",
+		"quad" => "1em",
+		"qquad" => "2em",
+		"!" => "-0.1667em",
+		_ => return None,
+	})
+}
+
+/// Maps a letter or digit to its styled version for a font command, i.e. `\mathbb`.
+fn styled_char(font: &str, c: char) -> char {
+	// letters missing from the Unicode math blocks live in the letterlike symbols block instead
+	let exception = match (font, c) {
+		("mathbb", 'C') => Some('ℂ'),
+		("mathbb", 'H') => Some('ℍ'),
+		("mathbb", 'N') => Some('ℕ'),
+		("mathbb", 'P') => Some('ℙ'),
+		("mathbb", 'Q') => Some('ℚ'),
+		("mathbb", 'R') => Some('ℝ'),
+		("mathbb", 'Z') => Some('ℤ'),
+		("mathcal", 'B') => Some('ℬ'),
+		("mathcal", 'E') => Some('ℰ'),
+		("mathcal", 'F') => Some('ℱ'),
+		("mathcal", 'H') => Some('ℋ'),
+		("mathcal", 'I') => Some('ℐ'),
+		("mathcal", 'L') => Some('ℒ'),
+		("mathcal", 'M') => Some('ℳ'),
+		("mathcal", 'R') => Some('ℛ'),
+		("mathcal", 'e') => Some('ℯ'),
+		("mathcal", 'g') => Some('ℊ'),
+		("mathcal", 'o') => Some('ℴ'),
+		("mathfrak", 'C') => Some('ℭ'),
+		("mathfrak", 'H') => Some('ℌ'),
+		("mathfrak", 'I') => Some('ℑ'),
+		("mathfrak", 'R') => Some('ℜ'),
+		("mathfrak", 'Z') => Some('ℨ'),
+		_ => None,
+	};
+	if let Some(c) = exception {
+		return c;
+	}
+	let (upper, lower, digit) = match font {
+		"mathbf" | "boldsymbol" => (0x1D400, 0x1D41A, Some(0x1D7CE)),
+		"mathbb" => (0x1D538, 0x1D552, Some(0x1D7D8)),
+		"mathcal" => (0x1D49C, 0x1D4B6, None),
+		"mathfrak" => (0x1D504, 0x1D51E, None),
+		"mathsf" => (0x1D5A0, 0x1D5BA, Some(0x1D7E2)),
+		"mathtt" => (0x1D670, 0x1D68A, Some(0x1D7F6)),
+		_ => return c,
+	};
+	let code = match c {
+		'A'..='Z' => upper + (c as u32 - 'A' as u32),
+		'a'..='z' => lower + (c as u32 - 'a' as u32),
+		'0'..='9' => match digit {
+			Some(digit) => digit + (c as u32 - '0' as u32),
+			None => return c,
+		},
+		_ => return c,
+	};
+	char::from_u32(code).unwrap_or(c)
+}
+
+/// Renders a stretchy fence around some content, i.e. for `\left`, or nothing if the fence is empty.
+fn fence(delimiter: &str) -> String {
+	if delimiter.is_empty() {
+		String::new()
+	} else {
+		format!(r#"<mo fence="true" stretchy="true">{delimiter}</mo>"#)
+	}
+}
+
+/// Renders an unsupported piece of TeX as an error.
+fn error(tex: &str) -> String {
+	format!("<merror><mtext>{}</mtext></merror>", escape_attribute(tex))
+}
+
+/// The base of an atom, which scripts are attached to.
+struct Base {
+	/// The base's MathML.
+	html: String,
+	/// Whether scripts are placed above and below the base rather than beside it.
+	limits: bool,
+	/// Whether the base is a named function, which is followed by an invisible function application operator.
+	function: bool,
+}
+
+impl Base {
+	/// Creates a base with scripts placed beside it.
+	fn new(html: String) -> Self {
+		Self {
+			html,
+			limits: false,
+			function: false,
+		}
+	}
+}
+
+/// Parser converting tokens into MathML.
+struct MathParser<'t> {
+	/// The tokens being parsed.
+	tokens: &'t [Token],
+	/// The position of the next token.
+	pos: usize,
+}
+
+impl<'t> MathParser<'t> {
+	/// Skips any whitespace, returning the next token.
+	fn peek(&mut self) -> Option<&'t Token> {
+		while self.tokens.get(self.pos) == Some(&Token::Space) {
+			self.pos += 1;
+		}
+		self.tokens.get(self.pos)
+	}
+
+	/// Skips any whitespace, consuming and returning the next token.
+	fn next(&mut self) -> Option<&'t Token> {
+		let token = self.peek();
+		if token.is_some() {
+			self.pos += 1;
+		}
+		token
+	}
+
+	/// Parses atoms until the end of the current group, table cell, or `\left` pair, or until the given closing character.
+	fn parse_row(&mut self, until: Option<char>) -> String {
+		let mut out = String::new();
+		loop {
+			match self.peek() {
+				None | Some(Token::Close | Token::Align) => break,
+				Some(Token::Char(c)) if Some(*c) == until => break,
+				Some(Token::Command(name)) if matches!(name.as_str(), "\\" | "right" | "end") => {
+					break;
+				}
+				_ => out.push_str(&self.parse_atom()),
+			}
+		}
+		out
+	}
+
+	/// Parses an atom along with its subscript and superscript, if any.
+	fn parse_atom(&mut self) -> String {
+		let Base {
+			html: base,
+			limits,
+			function,
+		} = self.parse_base();
+		let mut sub = None;
+		let mut sup = None;
+		loop {
+			match self.peek() {
+				Some(Token::Sub) if sub.is_none() => {
+					self.pos += 1;
+					sub = Some(self.parse_argument());
+				}
+				Some(Token::Sup) if sup.is_none() => {
+					self.pos += 1;
+					sup = Some(self.parse_argument());
+				}
+				Some(Token::Char('\'')) if sup.is_none() => {
+					let mut primes = String::new();
+					while self.peek() == Some(&Token::Char('\'')) {
+						self.pos += 1;
+						primes.push('′');
+					}
+					sup = Some(format!("<mo>{primes}</mo>"));
+				}
+				_ => break,
+			}
+		}
+		let (under, over, both) = if limits {
+			("munder", "mover", "munderover")
+		} else {
+			("msub", "msup", "msubsup")
+		};
+		let mut atom = match (sub, sup) {
+			(None, None) => base,
+			(Some(sub), None) => format!("<{under}>{base}{sub}</{under}>"),
+			(None, Some(sup)) => format!("<{over}>{base}{sup}</{over}>"),
+			(Some(sub), Some(sup)) => format!("<{both}>{base}{sub}{sup}</{both}>"),
+		};
+		if function {
+			atom.push_str("<mo>&#x2061;</mo>");
+		}
+		atom
+	}
+
+	/// Parses a command's argument, which is either a group or a single token.
+	fn parse_argument(&mut self) -> String {
+		match self.peek() {
+			Some(Token::Open) => {
+				self.pos += 1;
+				let row = self.parse_group();
+				format!("<mrow>{row}</mrow>")
+			}
+			Some(_) => {
+				let base = self.parse_base();
+				if base.function {
+					format!("<mrow>{}<mo>&#x2061;</mo></mrow>", base.html)
+				} else {
+					base.html
+				}
+			}
+			None => "<mrow></mrow>".to_string(),
+		}
+	}
+
+	/// Parses the rest of a group after its opening brace.
+	fn parse_group(&mut self) -> String {
+		let row = self.parse_row(None);
+		if self.peek() == Some(&Token::Close) {
+			self.pos += 1;
+		}
+		row
+	}
+
+	/// Reads an argument as plain text, i.e. for `\text`.
+	fn parse_text_argument(&mut self) -> String {
+		let mut text = String::new();
+		match self.next() {
+			Some(Token::Open) => {
+				let mut depth = 0;
+				while let Some(token) = self.tokens.get(self.pos) {
+					self.pos += 1;
+					match token {
+						Token::Open => depth += 1,
+						Token::Close if depth == 0 => break,
+						Token::Close => depth -= 1,
+						Token::Command(name) => match operator(name) {
+							Some(symbol) => text.push_str(symbol),
+							None => text.push_str(name),
+						},
+						Token::Number(number) => text.push_str(number),
+						Token::Char(c) => text.push(*c),
+						Token::Space => text.push(' '),
+						Token::Sup => text.push('^'),
+						Token::Sub => text.push('_'),
+						Token::Align => text.push('&'),
+					}
+				}
+			}
+			Some(Token::Char(c)) => text.push(*c),
+			Some(Token::Number(number)) => text.push_str(number),
+			_ => {}
+		}
+		text
+	}
+
+	/// Parses a delimiter following `\left`, `\right`, or a sizing command.
+	fn parse_delimiter(&mut self) -> String {
+		let delimiter = match self.next() {
+			Some(Token::Char('.')) => "",
+			Some(Token::Char(c)) => return escape_attribute(&c.to_string()),
+			Some(Token::Command(name)) => match operator(name) {
+				Some(symbol) => symbol,
+				None if name == "vert" => "|",
+				None if name == "Vert" => "‖",
+				None => "",
+			},
+			_ => "",
+		};
+		escape_attribute(delimiter)
+	}
+
+	/// Parses the base of an atom, returning whether its scripts are placed above and below it.
+	fn parse_base(&mut self) -> Base {
+		let Some(token) = self.next() else {
+			return Base::new(String::new());
+		};
+		match token {
+			Token::Number(number) => Base::new(format!("<mn>{number}</mn>")),
+			Token::Char(c) if c.is_alphabetic() => Base::new(format!("<mi>{c}</mi>")),
+			// TeX uses a proper minus sign for hyphens
+			Token::Char('-') => Base::new("<mo>−</mo>".to_string()),
+			Token::Char(c) => Base::new(format!("<mo>{}</mo>", escape_attribute(&c.to_string()))),
+			Token::Open => Base::new(format!("<mrow>{}</mrow>", self.parse_group())),
+			Token::Command(name) => self.parse_command(name),
+			// scripts without a base, or an argument missing before the end of a group, are left for the caller
+			Token::Sup | Token::Sub | Token::Close | Token::Align | Token::Space => {
+				self.pos -= 1;
+				Base::new("<mrow></mrow>".to_string())
+			}
+		}
+	}
+
+	/// Parses a command after its name.
+	fn parse_command(&mut self, name: &str) -> Base {
+		if let Some(symbol) = identifier(name) {
+			// capital Greek letters are upright
+			if symbol.chars().all(|c| ('Α'..='Ω').contains(&c)) {
+				return Base::new(format!(r#"<mi mathvariant="normal">{symbol}</mi>"#));
+			}
+			return Base::new(format!("<mi>{symbol}</mi>"));
+		}
+		if let Some(symbol) = operator(name) {
+			return Base::new(format!("<mo>{}</mo>", escape_attribute(symbol)));
+		}
+		if let Some((symbol, limits)) = large_operator(name) {
+			return Base {
+				html: format!(r#"<mo largeop="true">{symbol}</mo>"#),
+				limits,
+				function: false,
+			};
+		}
+		if let Some(limits) = function(name) {
+			return Base {
+				html: format!("<mi>{name}</mi>"),
+				limits,
+				function: true,
+			};
+		}
+		if let Some(width) = space(name) {
+			return Base::new(format!(r#"<mspace width="{width}"></mspace>"#));
+		}
+		if let Some((symbol, under)) = accent(name) {
+			let argument = self.parse_argument();
+			let stretchy =
+				name.starts_with("over") || name.starts_with("under") || name.starts_with("wide");
+			return Base::new(if under {
+				format!(
+					r#"<munder accentunder="true">{argument}<mo stretchy="{stretchy}">{symbol}</mo></munder>"#
+				)
+			} else {
+				format!(
+					r#"<mover accent="true">{argument}<mo stretchy="{stretchy}">{symbol}</mo></mover>"#
+				)
+			});
+		}
+		let html = match name {
+			"frac" | "dfrac" | "tfrac" | "cfrac" => {
+				let numerator = self.parse_argument();
+				let denominator = self.parse_argument();
+				format!("<mfrac>{numerator}{denominator}</mfrac>")
+			}
+			"binom" => {
+				let top = self.parse_argument();
+				let bottom = self.parse_argument();
+				format!(
+					r#"<mrow><mo>(</mo><mfrac linethickness="0">{top}{bottom}</mfrac><mo>)</mo></mrow>"#
+				)
+			}
+			"sqrt" => {
+				if self.peek() == Some(&Token::Char('[')) {
+					self.pos += 1;
+					let index = self.parse_row(Some(']'));
+					// an unclosed index ends with its group rather than swallowing what closed it
+					if self.peek() == Some(&Token::Char(']')) {
+						self.pos += 1;
+					}
+					let radicand = self.parse_argument();
+					format!("<mroot>{radicand}<mrow>{index}</mrow></mroot>")
+				} else {
+					format!("<msqrt>{}</msqrt>", self.parse_argument())
+				}
+			}
+			"text" | "textrm" | "textit" | "textbf" | "mbox" => {
+				format!(
+					"<mtext>{}</mtext>",
+					escape_attribute(&self.parse_text_argument())
+				)
+			}
+			"operatorname" => {
+				let name = escape_attribute(&self.parse_text_argument());
+				return Base {
+					html: format!("<mi>{name}</mi>"),
+					limits: false,
+					function: true,
+				};
+			}
+			"mathrm" => format!(
+				r#"<mi mathvariant="normal">{}</mi>"#,
+				escape_attribute(&self.parse_text_argument())
+			),
+			"mathit" => format!("<mi>{}</mi>", escape_attribute(&self.parse_text_argument())),
+			"mathbf" | "boldsymbol" | "mathbb" | "mathcal" | "mathfrak" | "mathsf" | "mathtt" => {
+				let text: String = self
+					.parse_text_argument()
+					.chars()
+					.map(|c| styled_char(name, c))
+					.collect();
+				format!("<mi>{}</mi>", escape_attribute(&text))
+			}
+			"left" => {
+				let open = self.parse_delimiter();
+				let row = self.parse_row(None);
+				let close = if self.peek() == Some(&Token::Command("right".to_string())) {
+					self.pos += 1;
+					self.parse_delimiter()
+				} else {
+					String::new()
+				};
+				format!("<mrow>{}{row}{}</mrow>", fence(&open), fence(&close))
+			}
+			"middle" => format!(r#"<mo stretchy="true">{}</mo>"#, self.parse_delimiter()),
+			"big" | "Big" | "bigg" | "Bigg" | "bigl" | "Bigl" | "biggl" | "Biggl" | "bigr"
+			| "Bigr" | "biggr" | "Biggr" | "bigm" | "Bigm" => {
+				format!(r#"<mo stretchy="false">{}</mo>"#, self.parse_delimiter())
+			}
+			"vert" => "<mo>|</mo>".to_string(),
+			"Vert" => "<mo>‖</mo>".to_string(),
+			"begin" => self.parse_environment(),
+			"displaystyle" | "textstyle" | "limits" | "nolimits" => String::new(),
+			name => error(&format!("\\{name}")),
+		};
+		Base::new(html)
+	}
+
+	/// Skips the contents of an unsupported environment, up to and including its `\end`.
+	fn skip_environment(&mut self) {
+		let mut depth = 0;
+		while let Some(token) = self.tokens.get(self.pos) {
+			self.pos += 1;
+			match token {
+				Token::Command(name) if name == "begin" => depth += 1,
+				Token::Command(name) if name == "end" && depth == 0 => {
+					self.parse_text_argument();
+					break;
+				}
+				Token::Command(name) if name == "end" => depth -= 1,
+				_ => {}
+			}
+		}
+	}
+
+	/// Parses an environment after `\begin`, i.e. a matrix.
+	fn parse_environment(&mut self) -> String {
+		let name = self.parse_text_argument();
+		if name == "array" {
+			// column specs aren't supported, so they're skipped
+			self.parse_text_argument();
+		}
+		let (open, close, column_align) = match name.as_str() {
+			"matrix" | "smallmatrix" | "array" => ("", "", None),
+			"pmatrix" => ("(", ")", None),
+			"bmatrix" => ("[", "]", None),
+			"Bmatrix" => ("{", "}", None),
+			"vmatrix" => ("|", "|", None),
+			"Vmatrix" => ("‖", "‖", None),
+			"cases" => ("{", "", Some("left left")),
+			"aligned" | "align" | "align*" | "split" => ("", "", Some("right left")),
+			"gathered" | "gather" | "gather*" => ("", "", None),
+			_ => {
+				self.skip_environment();
+				return error(&format!("\\begin{{{name}}}"));
+			}
+		};
+
+		let mut rows = Vec::new();
+		loop {
+			let mut cells = Vec::new();
+			loop {
+				cells.push(self.parse_row(None));
+				if self.peek() == Some(&Token::Align) {
+					self.pos += 1;
+				} else {
+					break;
+				}
+			}
+			rows.push(cells);
+			match self.next() {
+				Some(Token::Command(command)) if command == "\\" => {}
+				Some(Token::Command(command)) if command == "end" => {
+					self.parse_text_argument();
+					break;
+				}
+				// stray closing braces are skipped
+				Some(Token::Close) => {}
+				_ => break,
+			}
+		}
+		// a trailing line break doesn't start a new row
+		if rows
+			.last()
+			.is_some_and(|cells| cells.len() == 1 && cells[0].is_empty())
+		{
+			rows.pop();
+		}
+
+		let mut table = match column_align {
+			Some(align) => format!(r#"<mtable columnalign="{align}">"#),
+			None => "<mtable>".to_string(),
+		};
+		for cells in rows {
+			table.push_str("<mtr>");
+			for cell in cells {
+				table.push_str(&format!("<mtd>{cell}</mtd>"));
+			}
+			table.push_str("</mtr>");
+		}
+		table.push_str("</mtable>");
+		if open.is_empty() && close.is_empty() {
+			return table;
+		}
+		format!(
+			"<mrow>{}{table}{}</mrow>",
+			fence(&escape_attribute(open)),
+			fence(&escape_attribute(close))
+		)
+	}
+}
+
+/// Renders some TeX math to MathML, as a block if `display` is set.
+pub fn render_math(tex: &str, display: bool) -> String {
+	let tokens = tokenize(tex);
+	let mut parser = MathParser {
+		tokens: &tokens,
+		pos: 0,
+	};
+	let mut body = String::new();
+	loop {
+		body.push_str(&parser.parse_row(None));
+		// anything which ended the row early is out of place at the top level, so it's skipped
+		if parser.next().is_none() {
+			break;
+		}
+	}
+	let display = if display { r#" display="block""# } else { "" };
+	format!(
+		r#"<math{display}><semantics><mrow>{body}</mrow><annotation encoding="application/x-tex">{}</annotation></semantics></math>"#,
+		escape_attribute(tex)
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::render_math;
+
+	/// Renders some inline TeX, returning only the MathML inside its top level row.
+	fn body(tex: &str) -> String {
+		let math = render_math(tex, false);
+		let start = math.find("<semantics><mrow>").unwrap() + "<semantics><mrow>".len();
+		let end = math.rfind("</mrow><annotation").unwrap();
+		math[start..end].to_string()
+	}
+
+	#[test]
+	fn wraps_math() {
+		assert_eq!(
+			render_math("x", true),
+			r#"<math display="block"><semantics><mrow><mi>x</mi></mrow><annotation encoding="application/x-tex">x</annotation></semantics></math>"#
+		);
+		assert!(render_math("x", false).starts_with("<math><semantics>"));
+	}
+
+	#[test]
+	fn fractions() {
+		assert_eq!(
+			body(r"\frac{1}{2}"),
+			"<mfrac><mrow><mn>1</mn></mrow><mrow><mn>2</mn></mrow></mfrac>"
+		);
+		assert_eq!(body(r"\frac ab"), "<mfrac><mi>a</mi><mi>b</mi></mfrac>");
+		assert_eq!(
+			body(r"\frac{\frac{a}{b}}{c}"),
+			"<mfrac><mrow><mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac></mrow><mrow><mi>c</mi></mrow></mfrac>"
+		);
+		assert_eq!(
+			body(r"\frac{a}"),
+			"<mfrac><mrow><mi>a</mi></mrow><mrow></mrow></mfrac>"
+		);
+	}
+
+	#[test]
+	fn scripts() {
+		assert_eq!(body("x^2"), "<msup><mi>x</mi><mn>2</mn></msup>");
+		assert_eq!(body("x_i"), "<msub><mi>x</mi><mi>i</mi></msub>");
+		assert_eq!(
+			body("x_i^{n+1}"),
+			"<msubsup><mi>x</mi><mi>i</mi><mrow><mi>n</mi><mo>+</mo><mn>1</mn></mrow></msubsup>"
+		);
+		assert_eq!(
+			body(r"\sum_{i=0}^n"),
+			r#"<munderover><mo largeop="true">∑</mo><mrow><mi>i</mi><mo>=</mo><mn>0</mn></mrow><mi>n</mi></munderover>"#
+		);
+		assert_eq!(body("f''"), "<msup><mi>f</mi><mo>′′</mo></msup>");
+		assert_eq!(body("^2"), "<msup><mrow></mrow><mn>2</mn></msup>");
+		assert_eq!(body("x^"), "<msup><mi>x</mi><mrow></mrow></msup>");
+	}
+
+	#[test]
+	fn environments() {
+		assert_eq!(
+			body(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}"),
+			r#"<mrow><mo fence="true" stretchy="true">(</mo><mtable><mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr><mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr></mtable><mo fence="true" stretchy="true">)</mo></mrow>"#
+		);
+		assert_eq!(
+			body(r"\begin{matrix} a \\ \end{matrix}"),
+			"<mtable><mtr><mtd><mi>a</mi></mtd></mtr></mtable>"
+		);
+		assert_eq!(
+			body(r"\begin{cases} 1 & x > 0 \end{cases}"),
+			r#"<mrow><mo fence="true" stretchy="true">{</mo><mtable columnalign="left left"><mtr><mtd><mn>1</mn></mtd><mtd><mi>x</mi><mo>&gt;</mo><mn>0</mn></mtd></mtr></mtable></mrow>"#
+		);
+		assert_eq!(
+			body(r"\begin{array}{cc} a & b \end{array}"),
+			"<mtable><mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr></mtable>"
+		);
+	}
+
+	#[test]
+	fn escaping() {
+		assert_eq!(body("a<b"), "<mi>a</mi><mo>&lt;</mo><mi>b</mi>");
+		assert_eq!(body(r"\&"), "<mo>&amp;</mo>");
+		assert_eq!(body(r"\text{a < b & c}"), "<mtext>a &lt; b &amp; c</mtext>");
+		assert_eq!(body(r"\mathrm{<}"), r#"<mi mathvariant="normal">&lt;</mi>"#);
+		assert_eq!(
+			body(r"\left< x \right>"),
+			r#"<mrow><mo fence="true" stretchy="true">&lt;</mo><mi>x</mi><mo fence="true" stretchy="true">&gt;</mo></mrow>"#
+		);
+		assert!(
+			render_math("a<b", false)
+				.contains(r#"<annotation encoding="application/x-tex">a&lt;b</annotation>"#)
+		);
+	}
+
+	#[test]
+	fn malformed() {
+		assert_eq!(body("{x"), "<mrow><mi>x</mi></mrow>");
+		assert_eq!(body("x}y"), "<mi>x</mi><mi>y</mi>");
+		assert_eq!(body(r"\text{abc"), "<mtext>abc</mtext>");
+		assert_eq!(
+			body(r"\left( x"),
+			r#"<mrow><mo fence="true" stretchy="true">(</mo><mi>x</mi></mrow>"#
+		);
+		assert_eq!(body(r"x \right)"), "<mi>x</mi><mo>)</mo>");
+		assert_eq!(
+			body(r"\begin{matrix} a & b"),
+			"<mtable><mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr></mtable>"
+		);
+		assert_eq!(
+			body(r"{\sqrt[3 x} y"),
+			"<mrow><mroot><mrow></mrow><mrow><mn>3</mn><mi>x</mi></mrow></mroot></mrow><mi>y</mi>"
+		);
+		assert_eq!(body("x &"), "<mi>x</mi>");
+	}
+
+	#[test]
+	fn unknown_commands() {
+		assert_eq!(body(r"\foo"), r"<merror><mtext>\foo</mtext></merror>");
+		assert_eq!(
+			body(r"\foo{x}"),
+			r"<merror><mtext>\foo</mtext></merror><mrow><mi>x</mi></mrow>"
+		);
+		assert_eq!(
+			body(r"\begin{foo} a & b \end{foo} c"),
+			r"<merror><mtext>\begin{foo}</mtext></merror><mi>c</mi>"
+		);
+		assert_eq!(
+			body(r"\begin{foo} \begin{bar} \end{bar} \end{foo} c"),
+			r"<merror><mtext>\begin{foo}</mtext></merror><mi>c</mi>"
+		);
+	}
+}
@@ -1,17 +1,20 @@
 use std::{
-	collections::BTreeMap,
+	collections::{BTreeMap, BTreeSet, HashMap},
 	path::{Path, PathBuf},
 };
 
 use eyre::Context;
-use rss::{ChannelBuilder, ItemBuilder, validation::Validate};
 use serde::{Deserialize, Serialize};
-use time::{OffsetDateTime, format_description::well_known::Rfc2822};
+use time::{Month, OffsetDateTime};
+use walkdir::WalkDir;
 
 use crate::{
-	PageMetadata,
+	AlternateLink, PageMetadata, TocEntry,
 	builder::SiteBuilder,
+	cache::CacheKey,
+	feed::{FeedAuthor, FeedFormatConfig, FeedInfo},
 	frontmatter::FrontMatterRequired,
+	i18n::Translation,
 	link_list::Link,
 	util::{self, format_timestamp},
 };
@@ -36,8 +39,34 @@ pub struct ResourceMetadata {
 	/// Whether the resource is a draft. Drafts can be committed without being published to the live site.
 	#[serde(default)]
 	pub draft: bool,
+	/// Overrides for the site's Markdown extensions, if any.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub markdown: Option<serde_yaml_ng::Mapping>,
+	/// The name of the series the resource is part of, if any.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub series: Option<String>,
+	/// The resource's position in its series. Resources without one come after those with one, ordered by timestamp.
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub series_part: Option<u32>,
+	/// The resource's table of contents. Set by the builder, not your resource metadata.
+	#[serde(skip)]
+	pub toc: Vec<TocEntry>,
+	/// The resource's language, if the site is multilingual. Set by the builder, not your resource metadata.
+	#[serde(skip)]
+	pub language: Option<String>,
 }
 
+/// Keys of [`ResourceTemplateData`] which are written next to a resource's metadata, so they can't be used by its custom front matter.
+const RESERVED_KEYS: &[&str] = &[
+	"url",
+	"lang",
+	"toc",
+	"series_info",
+	"nav",
+	"tag_neighbors",
+	"related",
+];
+
 #[derive(Debug, Serialize)]
 pub struct ResourceTemplateData<'r> {
 	/// The resource's metadata.
@@ -45,8 +74,61 @@ pub struct ResourceTemplateData<'r> {
 	pub resource: &'r FrontMatterRequired<ResourceMetadata>,
 	/// The resource's ID.
 	pub id: String,
+	/// The URL the resource is served at.
+	pub url: String,
+	/// The resource's language, if the site is multilingual.
+	pub lang: Option<&'r str>,
 	/// The resource's timestamp in a readable format.
 	pub readable_timestamp: String,
+	/// The resource's table of contents.
+	pub toc: &'r [TocEntry],
+	/// The series the resource is part of, if any. Named so it doesn't clash with the `series` name from the resource's metadata.
+	pub series_info: Option<SeriesTemplateData<'r>>,
+	/// The resources published before and after this one.
+	pub nav: ResourceNeighbors<'r>,
+	/// The resources published before and after this one with each of its tags, by tag.
+	pub tag_neighbors: BTreeMap<&'r str, ResourceNeighbors<'r>>,
+	/// Related resources, ranked by how many tags they share with this one and then by recency.
+	pub related: Vec<ResourceLink<'r>>,
+}
+
+/// The resources published before and after a resource.
+#[derive(Debug, Serialize)]
+pub struct ResourceNeighbors<'r> {
+	/// The next older resource, if any.
+	pub previous: Option<ResourceLink<'r>>,
+	/// The next newer resource, if any.
+	pub next: Option<ResourceLink<'r>>,
+}
+
+/// A link to another resource, used to navigate between resources.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResourceLink<'r> {
+	/// The resource's ID.
+	pub id: &'r str,
+	/// The resource's title.
+	pub title: &'r str,
+	/// The URL the resource is served at.
+	pub url: String,
+}
+
+/// Template data for the series a resource is part of.
+#[derive(Debug, Serialize)]
+pub struct SeriesTemplateData<'r> {
+	/// The series' name.
+	pub name: &'r str,
+	/// The URL of the series' index page.
+	pub url: String,
+	/// The resource's position in the series, starting from 1.
+	pub part: usize,
+	/// The number of resources in the series.
+	pub total: usize,
+	/// The previous resource in the series, if any.
+	pub previous: Option<ResourceLink<'r>>,
+	/// The next resource in the series, if any.
+	pub next: Option<ResourceLink<'r>>,
+	/// Every resource in the series, in order.
+	pub resources: Vec<ResourceLink<'r>>,
 }
 
 /// struct for adding custom meta content embeds
@@ -100,7 +182,10 @@ struct ResourceListTemplateData<'r> {
 	resources: Vec<&'r ResourceTemplateData<'r>>,
 	has_tags: bool,
 	tag: Option<&'r str>,
+	series: Option<&'r str>,
 	rss_enabled: bool,
+	rss_url: Option<&'r str>,
+	feeds: &'r [AlternateLink],
 	page: usize,
 	page_max: usize,
 	previous: Option<usize>,
@@ -124,16 +209,32 @@ pub struct ResourceBuilderConfig {
 	pub tag_list_template: String,
 	/// The resource type's RSS info, if enabled.
 	pub rss: Option<ResourceRSSBuilderConfig>,
+	/// Config for the resource type's date-based archive pages, if enabled.
+	#[serde(default)]
+	pub archive: Option<ResourceArchiveConfig>,
 	/// Title for the main list of resources.
 	pub list_title: String,
 	/// Title for the page containing a list of tags.
 	pub tag_list_title: String,
+	/// Title for the page containing a list of series. Defaults to the resource type's plural name followed by "series".
+	#[serde(default)]
+	pub series_list_title: Option<String>,
 	/// Name for the resource type in plural.
 	pub resource_name_plural: String,
 	/// The number of resources to display on a single page.
 	pub resources_per_page: usize,
 	/// The format to use for the readable timestamp.
 	pub timestamp_format: String,
+	/// The number of related resources to give each resource's template.
+	#[serde(default = "ResourceBuilderConfig::default_related_count")]
+	pub related_count: usize,
+}
+
+impl ResourceBuilderConfig {
+	/// The default number of related resources.
+	pub fn default_related_count() -> usize {
+		3
+	}
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -144,6 +245,153 @@ pub struct ResourceRSSBuilderConfig {
 	pub title: String,
 	/// The description for the RSS feed.
 	pub description: String,
+	/// The feed's author info, if any.
+	#[serde(default)]
+	pub author: Option<FeedAuthor>,
+	/// Config for an Atom feed built alongside the RSS feed, if enabled.
+	#[serde(default)]
+	pub atom: Option<FeedFormatConfig>,
+	/// Config for a JSON feed built alongside the RSS feed, if enabled.
+	#[serde(default)]
+	pub json: Option<FeedFormatConfig>,
+	/// Whether to build a feed for each tag in addition to the main feed.
+	#[serde(default)]
+	pub per_tag: bool,
+}
+
+/// Config for a resource type's date-based archive pages.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceArchiveConfig {
+	/// The template used to render a single year or month's resources.
+	pub template: String,
+	/// Title for the page containing a list of archive pages.
+	pub title: String,
+	/// Whether to build a page for each month in addition to each year.
+	#[serde(default = "ResourceArchiveConfig::default_months")]
+	pub months: bool,
+}
+
+impl ResourceArchiveConfig {
+	/// Monthly archive pages are built by default.
+	pub fn default_months() -> bool {
+		true
+	}
+}
+
+/// Template data for a single year or month's archive page.
+#[derive(Debug, Serialize)]
+struct ArchiveTemplateData<'r> {
+	/// The resources published in the year or month, from newest to oldest.
+	resources: Vec<&'r ResourceTemplateData<'r>>,
+	/// The archive page's year.
+	year: i32,
+	/// The archive page's month from 1 to 12, if it's a month's page.
+	month: Option<u8>,
+	/// The name of the archive page's month, if it's a month's page.
+	month_name: Option<String>,
+	/// Links to each month in the year with resources, on year pages with monthly pages enabled.
+	months: Vec<Link<'r>>,
+	/// URL of the page containing a list of archive pages.
+	archive_url: &'r str,
+}
+
+/// A list of resources a resource can be navigated between, i.e. the resources with one of its tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum ListKey<'a> {
+	/// Every resource in a language.
+	Language(Option<&'a str>),
+	/// The resources with a tag in a language.
+	Tag(Option<&'a str>, &'a str),
+	/// The resources in a series in a language.
+	Series(Option<&'a str>, &'a str),
+}
+
+/// A resource's position in one of the lists in [`ResourceOrder`].
+#[derive(Debug, Clone, Copy)]
+struct ListPosition {
+	/// The index of the list.
+	list: usize,
+	/// The resource's index in the list.
+	index: usize,
+}
+
+/// Where a resource appears in the lists in [`ResourceOrder`].
+#[derive(Debug)]
+struct ResourcePositions {
+	/// The resource's position among every resource in its language.
+	language: ListPosition,
+	/// The resource's position among the resources with each of its tags, in the same order as its tags.
+	tags: Vec<ListPosition>,
+	/// The resource's position in its series, if any.
+	series: Option<ListPosition>,
+}
+
+/// The orderings used to navigate between the loaded resources, computed whenever they change.
+#[derive(Debug, Default)]
+struct ResourceOrder {
+	/// Lists of indices into the loaded resources. Languages and tags are sorted from newest to oldest, and series in series order.
+	lists: Vec<Vec<usize>>,
+	/// Where each loaded resource appears in the lists, by index.
+	positions: Vec<ResourcePositions>,
+}
+
+impl ResourceOrder {
+	/// Computes the orderings for resources sorted from newest to oldest.
+	fn new(resources: &[(String, FrontMatterRequired<ResourceMetadata>)]) -> Self {
+		let mut lists: Vec<Vec<usize>> = Vec::new();
+		let mut ids: HashMap<ListKey, usize> = HashMap::new();
+		let mut push = |key, resource| {
+			let list = *ids.entry(key).or_insert_with(|| {
+				lists.push(Vec::new());
+				lists.len() - 1
+			});
+			let entries = &mut lists[list];
+			// resources with a tag more than once are only listed once
+			if entries.last() != Some(&resource) {
+				entries.push(resource);
+			}
+			ListPosition {
+				list,
+				index: entries.len() - 1,
+			}
+		};
+
+		let mut positions = Vec::with_capacity(resources.len());
+		let mut series_lists = Vec::new();
+		for (index, (_, resource)) in resources.iter().enumerate() {
+			let data = resource.data();
+			let language = data.language.as_deref();
+			let language_position = push(ListKey::Language(language), index);
+			let tags = data
+				.tags
+				.iter()
+				.map(|tag| push(ListKey::Tag(language, tag), index))
+				.collect();
+			// series are sorted once every resource has been added
+			if let Some(series) = &data.series {
+				series_lists.push(push(ListKey::Series(language, series), index).list);
+			}
+			positions.push(ResourcePositions {
+				language: language_position,
+				tags,
+				series: None,
+			});
+		}
+
+		series_lists.sort();
+		series_lists.dedup();
+		for list in series_lists {
+			lists[list].sort_by_key(|&i| {
+				let data = resources[i].1.data();
+				(data.series_part.is_none(), data.series_part, data.timestamp)
+			});
+			for (index, &resource) in lists[list].iter().enumerate() {
+				positions[resource].series = Some(ListPosition { list, index });
+			}
+		}
+
+		Self { lists, positions }
+	}
 }
 
 /// Helper to genericize resource building.
@@ -153,6 +401,8 @@ pub struct ResourceBuilder {
 	pub config: ResourceBuilderConfig,
 	/// The currently loaded resource metadata.
 	pub loaded_metadata: Vec<(String, FrontMatterRequired<ResourceMetadata>)>,
+	/// The orderings of the loaded resources.
+	order: ResourceOrder,
 }
 
 impl ResourceBuilder {
@@ -161,32 +411,67 @@ impl ResourceBuilder {
 		Self {
 			config,
 			loaded_metadata: Default::default(),
+			order: Default::default(),
 		}
 	}
 
-	/// Gets a resource's ID from its path.
-	fn get_id(path: &Path) -> String {
-		path.with_extension("")
-			.file_name()
-			.expect("Should never fail")
-			.to_string_lossy()
-			.into_owned()
+	/// Gets the path resources of this type are loaded from.
+	fn source_path(&self, builder: &SiteBuilder) -> PathBuf {
+		builder
+			.site
+			.site_path
+			.join(crate::RESOURCES_PATH)
+			.join(&self.config.source_path)
+	}
+
+	/// Gets a resource's ID and language from its path, relative to the resource type's source path.
+	fn get_id(&self, builder: &SiteBuilder, path: &Path) -> (String, Option<String>) {
+		let path = path.with_extension("");
+		let id = match path.strip_prefix(self.source_path(builder)) {
+			Ok(relative) => relative
+				.to_string_lossy()
+				.replace(std::path::MAIN_SEPARATOR, "/"),
+			_ => path
+				.file_name()
+				.expect("Should never fail")
+				.to_string_lossy()
+				.into_owned(),
+		};
+		match &builder.site.config.i18n {
+			Some(i18n) => {
+				let (id, language) = i18n.split_language(&id);
+				(id.to_string(), Some(language.to_string()))
+			}
+			None => (id, None),
+		}
 	}
 
 	/// Loads resource metadata from the given path.
 	fn load(
+		&self,
 		builder: &SiteBuilder,
 		path: &Path,
 	) -> eyre::Result<(String, FrontMatterRequired<ResourceMetadata>)> {
-		let id = Self::get_id(path);
+		let (id, language) = self.get_id(builder, path);
 
 		let input = std::fs::read_to_string(path)?;
 		let mut page = FrontMatterRequired::<ResourceMetadata>::parse(input)
 			.wrap_err_with(|| eyre::eyre!("Failed to parse resource front matter"))?;
+		if let serde_yaml_ng::Value::Mapping(inner) = &page.data().inner
+			&& let Some(key) = RESERVED_KEYS.iter().find(|key| inner.contains_key(**key))
+		{
+			eyre::bail!(
+				"front matter key {key:?} is reserved for the data webdog gives resource templates"
+			);
+		}
 
-		*page.content_mut() = util::render_markdown(builder, &page.content)?;
+		let rendered =
+			util::render_markdown_cached(builder, &page.content, page.data().markdown.as_ref())?;
+		*page.content_mut() = rendered.html;
 
 		let data = page.data_mut();
+		data.toc = rendered.toc;
+		data.language = language;
 		if let Some(cdn_file) = &data.cdn_file {
 			data.cdn_file = Some(builder.site.config.cdn_url(cdn_file)?.to_string());
 		}
@@ -194,127 +479,435 @@ impl ResourceBuilder {
 		Ok((id, page))
 	}
 
-	/// Loads all resource metadata from the given config.
+	/// Loads all resource metadata from the given config, including resources in subdirectories.
 	pub fn load_all(&mut self, builder: &SiteBuilder) -> eyre::Result<()> {
-		let lmd = &mut self.loaded_metadata;
-		lmd.clear();
-		for e in builder
-			.site
-			.site_path
-			.join(crate::RESOURCES_PATH)
-			.join(&self.config.source_path)
-			.read_dir()?
-		{
-			let p = e?.path();
-			if let Some("md") = p.extension().and_then(|e| e.to_str()) {
-				let (id, metadata) = Self::load(builder, &p)?;
-				if !builder.serving && metadata.data.as_ref().expect("should never fail").draft {
+		let mut lmd = Vec::new();
+		for e in WalkDir::new(self.source_path(builder)).sort_by_file_name() {
+			let e = e?;
+			let p = e.path();
+			if e.file_type().is_file()
+				&& let Some("md") = p.extension().and_then(|e| e.to_str())
+			{
+				let (id, metadata) = self
+					.load(builder, p)
+					.wrap_err_with(|| format!("Failed to load resource at {}", p.display()))?;
+				if !builder.serving && metadata.data().draft {
 					continue;
 				}
 				lmd.push((id, metadata));
 			}
 		}
-		lmd.sort_by(|a, b| {
-			b.1.data
-				.as_ref()
-				.expect("should never fail")
-				.timestamp
-				.cmp(&a.1.data.as_ref().expect("should never fail").timestamp)
-		});
+		self.loaded_metadata = lmd;
+		self.sort();
 		Ok(())
 	}
 
+	/// Reloads a single resource from the given path, removing it if the file no longer exists.
+	///
+	/// Returns the resource's ID and language if it was removed.
+	pub fn reload_one(
+		&mut self,
+		builder: &SiteBuilder,
+		path: &Path,
+	) -> eyre::Result<Option<(String, Option<String>)>> {
+		let (id, language) = self.get_id(builder, path);
+		let loaded = if path.is_file()
+			&& let Some("md") = path.extension().and_then(|e| e.to_str())
+		{
+			Some(self.load(builder, path)?)
+		} else {
+			None
+		};
+		let matches = |(i, r): &(String, FrontMatterRequired<ResourceMetadata>)| {
+			*i == id && r.data().language == language
+		};
+		let existed = self.loaded_metadata.iter().any(matches);
+		self.loaded_metadata.retain(|r| !matches(r));
+		if let Some((id, metadata)) = loaded
+			&& (builder.serving || !metadata.data().draft)
+		{
+			self.loaded_metadata.push((id, metadata));
+			self.sort();
+			return Ok(None);
+		}
+		self.sort();
+		Ok(existed.then_some((id, language)))
+	}
+
+	/// Sorts the loaded resources from newest to oldest, then recomputes their orderings.
+	fn sort(&mut self) {
+		self.loaded_metadata
+			.sort_by_key(|(_, r)| std::cmp::Reverse(r.data().timestamp));
+		self.order = ResourceOrder::new(&self.loaded_metadata);
+	}
+
+	/// Gets every template the resource type depends on.
+	pub(crate) fn template_dependencies(&self, builder: &SiteBuilder) -> BTreeSet<String> {
+		let mut templates = vec![
+			self.config.resource_template.clone(),
+			self.config.resource_list_template.clone(),
+			self.config.tag_list_template.clone(),
+		];
+		if let Some(rss) = &self.config.rss {
+			templates.push(rss.template.clone());
+			for format in [&rss.atom, &rss.json].into_iter().flatten() {
+				templates.extend(format.template.clone());
+			}
+		}
+		if let Some(archive) = &self.config.archive {
+			templates.push(archive.template.clone());
+		}
+		for (_, resource) in &self.loaded_metadata {
+			templates.extend(crate::dependencies::find_partials(&resource.content));
+		}
+		builder.template_dependencies(templates.iter().map(|t| t.as_str()))
+	}
+
+	/// Gets the alternate links for the resource type's main feeds in the language with the given prefix.
+	fn feed_alternates(&self, prefix: &str) -> Vec<AlternateLink> {
+		match &self.config.rss {
+			Some(rss) => crate::feed::feed_alternates(
+				rss,
+				&rss.title,
+				&format!("{prefix}{}", self.config.output_path_lists),
+			),
+			None => Vec::new(),
+		}
+	}
+
 	/// Gets a resource's build path.
-	fn build_path(&self, base_path: &Path, id: &str) -> PathBuf {
-		base_path
+	pub(crate) fn build_path(
+		&self,
+		builder: &SiteBuilder,
+		id: &str,
+		language: Option<&str>,
+	) -> PathBuf {
+		builder
+			.build_path
+			.join(builder.site.config.language_prefix(language))
 			.join(&self.config.output_path_resources)
 			.join(id)
 			.with_extension("html")
 	}
 
+	/// Gets the URL a resource is served at.
+	pub(crate) fn url(&self, builder: &SiteBuilder, id: &str, language: Option<&str>) -> String {
+		format!(
+			"/{}{}/{id}",
+			builder.site.config.language_prefix(language),
+			self.config.output_path_resources
+		)
+	}
+
+	/// Gets the title for the page containing a list of series.
+	fn series_list_title(&self) -> String {
+		self.config
+			.series_list_title
+			.clone()
+			.unwrap_or_else(|| format!("{} series", self.config.resource_name_plural))
+	}
+
+	/// Gets the URL of a series' index page.
+	fn series_url(&self, builder: &SiteBuilder, series: &str, language: Option<&str>) -> String {
+		format!(
+			"/{}{}/series/{}/",
+			builder.site.config.language_prefix(language),
+			self.config.output_path_resources,
+			util::slugify(series)
+		)
+	}
+
+	/// Gets a link to the loaded resource at the given index.
+	fn link(&self, builder: &SiteBuilder, index: usize) -> ResourceLink<'_> {
+		let (id, resource) = &self.loaded_metadata[index];
+		let data = resource.data();
+		ResourceLink {
+			id,
+			title: &data.title,
+			url: self.url(builder, id, data.language.as_deref()),
+		}
+	}
+
+	/// Gets the template data for the series the loaded resource at the given index is part of.
+	fn series_data(&self, builder: &SiteBuilder, index: usize) -> Option<SeriesTemplateData<'_>> {
+		let data = self.loaded_metadata[index].1.data();
+		let name = data.series.as_deref()?;
+		let position = self.order.positions[index].series?;
+		let resources = &self.order.lists[position.list];
+		Some(SeriesTemplateData {
+			name,
+			url: self.series_url(builder, name, data.language.as_deref()),
+			part: position.index + 1,
+			total: resources.len(),
+			previous: position
+				.index
+				.checked_sub(1)
+				.map(|i| self.link(builder, resources[i])),
+			next: resources
+				.get(position.index + 1)
+				.map(|&i| self.link(builder, i)),
+			resources: resources.iter().map(|&i| self.link(builder, i)).collect(),
+		})
+	}
+
+	/// Gets the resources published before and after a resource in one of its lists.
+	fn neighbors(&self, builder: &SiteBuilder, position: ListPosition) -> ResourceNeighbors<'_> {
+		// resources are sorted from newest to oldest
+		let resources = &self.order.lists[position.list];
+		ResourceNeighbors {
+			previous: resources
+				.get(position.index + 1)
+				.map(|&i| self.link(builder, i)),
+			next: position
+				.index
+				.checked_sub(1)
+				.map(|i| self.link(builder, resources[i])),
+		}
+	}
+
+	/// Gets the resources related to the loaded resource at the given index in the same language, ranked by how many tags they share and then by recency.
+	fn related(&self, builder: &SiteBuilder, index: usize) -> Vec<ResourceLink<'_>> {
+		let positions = &self.order.positions[index];
+		let mut tag_lists: Vec<_> = positions.tags.iter().map(|p| p.list).collect();
+		tag_lists.sort();
+		tag_lists.dedup();
+		let mut shared: HashMap<usize, usize> = HashMap::new();
+		for list in tag_lists {
+			for &other in &self.order.lists[list] {
+				if other != index {
+					*shared.entry(other).or_default() += 1;
+				}
+			}
+		}
+		// ties go to the newest resource, which comes first in its language
+		let mut shared: Vec<_> = shared.into_iter().collect();
+		shared.sort_by_key(|&(other, count)| {
+			(
+				std::cmp::Reverse(count),
+				self.order.positions[other].language.index,
+			)
+		});
+		let mut related: Vec<_> = shared
+			.into_iter()
+			.map(|(other, _)| other)
+			.take(self.config.related_count)
+			.collect();
+		// resources without any shared tags fill in the rest, newest first
+		for &other in &self.order.lists[positions.language.list] {
+			if related.len() >= self.config.related_count {
+				break;
+			}
+			if other != index && !related.contains(&other) {
+				related.push(other);
+			}
+		}
+		related
+			.into_iter()
+			.map(|other| self.link(builder, other))
+			.collect()
+	}
+
+	/// Gets the template data for the loaded resource at the given index.
+	pub(crate) fn template_data(
+		&self,
+		builder: &SiteBuilder,
+		index: usize,
+	) -> eyre::Result<ResourceTemplateData<'_>> {
+		let (id, resource) = &self.loaded_metadata[index];
+		let data = resource.data();
+		let positions = &self.order.positions[index];
+		Ok(ResourceTemplateData {
+			resource,
+			id: id.clone(),
+			url: self.url(builder, id, data.language.as_deref()),
+			lang: data.language.as_deref(),
+			readable_timestamp: format_timestamp(data.timestamp, &self.config.timestamp_format)?,
+			toc: &data.toc,
+			series_info: self.series_data(builder, index),
+			nav: self.neighbors(builder, positions.language),
+			tag_neighbors: data
+				.tags
+				.iter()
+				.zip(&positions.tags)
+				.map(|(tag, position)| (tag.as_str(), self.neighbors(builder, *position)))
+				.collect(),
+			related: self.related(builder, index),
+		})
+	}
+
+	/// Gets every translation of a resource, including itself, sorted by language.
+	///
+	/// Returns nothing if the resource hasn't been translated.
+	fn translations(&self, builder: &SiteBuilder, id: &str) -> Vec<Translation> {
+		let Some(i18n) = &builder.site.config.i18n else {
+			return Vec::new();
+		};
+		let mut translations: Vec<_> = self
+			.loaded_metadata
+			.iter()
+			.filter(|(i, _)| i == id)
+			.filter_map(|(_, resource)| {
+				let language = resource.data().language.as_deref()?;
+				Some(Translation {
+					code: language.to_string(),
+					name: i18n
+						.languages
+						.get(language)
+						.map(|l| l.name.clone())
+						.unwrap_or_default(),
+					url: self.url(builder, id, Some(language)),
+				})
+			})
+			.collect();
+		if translations.len() < 2 {
+			return Vec::new();
+		}
+		translations.sort_by(|a, b| a.code.cmp(&b.code));
+		translations
+	}
+
 	/// Builds a single resource page.
 	fn build(
 		&self,
 		builder: &SiteBuilder,
-		id: String,
-		resource: &FrontMatterRequired<ResourceMetadata>,
+		key: CacheKey,
+		template_data: &ResourceTemplateData,
 	) -> eyre::Result<()> {
-		let out_path = self.build_path(&builder.build_path, &id);
-
+		let resource = template_data.resource;
 		let data = resource.data();
-		let out = builder.build_page_raw(
-			PageMetadata {
-				template: Some(self.config.resource_template.clone()),
-				title: Some(data.title.clone()),
-				embed: Some(EmbedMetadata {
-					title: data.title.clone(),
-					description: data.desc.clone(),
-					image: if let Some(cdn_file) = &data.cdn_file {
-						Some(builder.site.config.cdn_url(cdn_file)?.to_string())
-					} else {
-						None
+		let language = data.language.as_deref();
+		let out_path = self.build_path(builder, &template_data.id, language);
+		let translations = self.translations(builder, &template_data.id);
+
+		builder.write_output(
+			&out_path,
+			key.add("resource")
+				.add_serialized(template_data)?
+				.add_serialized(&translations)?,
+			|| {
+				builder.build_page_raw(
+					PageMetadata {
+						template: Some(self.config.resource_template.clone()),
+						title: Some(data.title.clone()),
+						embed: Some(EmbedMetadata {
+							title: data.title.clone(),
+							description: data.desc.clone(),
+							image: if let Some(cdn_file) = &data.cdn_file {
+								Some(builder.site.config.cdn_url(cdn_file)?.to_string())
+							} else {
+								None
+							},
+							theme_color: None,
+							large_image: true,
+						}),
+						alternates: self
+							.feed_alternates(&builder.site.config.language_prefix(language)),
+						language: language.map(str::to_owned),
+						translations,
+						..Default::default()
 					},
-					theme_color: None,
-					large_image: true,
-				}),
-				..Default::default()
-			},
-			"",
-			ResourceTemplateData {
-				resource,
-				id,
-				readable_timestamp: format_timestamp(
-					resource.data().timestamp,
-					&self.config.timestamp_format,
-				)?,
+					"",
+					template_data,
+				)
 			},
 		)?;
-		std::fs::write(out_path, out)?;
 
 		Ok(())
 	}
 
 	pub fn build_all(&self, builder: &SiteBuilder) -> eyre::Result<()> {
-		let out_short = builder.build_path.join(&self.config.output_path_resources);
-		let out_long = builder.build_path.join(&self.config.output_path_lists);
+		let key = builder.cache_key_with_templates(&self.template_dependencies(builder));
 
-		if !out_short.exists() {
-			std::fs::create_dir_all(&out_short)?;
+		// each resource's template data is shared by its page, lists, and feeds
+		let data = (0..self.loaded_metadata.len())
+			.map(|index| self.template_data(builder, index))
+			.collect::<eyre::Result<Vec<_>>>()?;
+		for template_data in &data {
+			self.build(builder, key.clone(), template_data)?;
 		}
-		if !out_long.exists() {
-			std::fs::create_dir_all(&out_long)?;
+
+		// Resources are listed separately for each language, and the default language's lists are always built
+		let mut languages: BTreeMap<Option<&str>, Vec<&ResourceTemplateData>> = BTreeMap::new();
+		if let Some(i18n) = &builder.site.config.i18n {
+			languages.insert(Some(i18n.default_language.as_str()), Vec::new());
+		} else {
+			languages.insert(None, Vec::new());
+		}
+		for template_data in &data {
+			languages
+				.entry(template_data.lang)
+				.or_default()
+				.push(template_data);
 		}
 
-		let lmd = &self.loaded_metadata;
+		let list_translations = match &builder.site.config.i18n {
+			Some(i18n) if languages.len() > 1 => languages
+				.keys()
+				.flatten()
+				.map(|language| Translation {
+					code: language.to_string(),
+					name: i18n
+						.languages
+						.get(*language)
+						.map(|l| l.name.clone())
+						.unwrap_or_default(),
+					url: format!(
+						"/{}{}/",
+						i18n.prefix(language),
+						self.config.output_path_lists
+					),
+				})
+				.collect(),
+			_ => Vec::new(),
+		};
 
-		for (id, resource) in lmd.iter() {
-			self.build(builder, id.clone(), resource)?;
+		for (language, data) in &languages {
+			self.build_lists(builder, &key, *language, data, &list_translations)?;
 		}
 
-		let resource_has_tags = lmd.iter().any(|(_, r)| !r.data().tags.is_empty());
+		Ok(())
+	}
+
+	/// Builds the lists, tag pages, and feeds for the resources in a single language.
+	fn build_lists(
+		&self,
+		builder: &SiteBuilder,
+		key: &CacheKey,
+		language: Option<&str>,
+		data: &[&ResourceTemplateData],
+		translations: &[Translation],
+	) -> eyre::Result<()> {
+		let prefix = builder.site.config.language_prefix(language);
+		let output_path_resources = format!("{prefix}{}", self.config.output_path_resources);
+		let output_path_lists = format!("{prefix}{}", self.config.output_path_lists);
+		let out_short = builder.build_path.join(&output_path_resources);
+		let out_long = builder.build_path.join(&output_path_lists);
 
-		let mut data = Vec::with_capacity(lmd.len());
-		for (id, resource) in lmd.iter() {
-			data.push(ResourceTemplateData {
-				resource,
-				id: id.clone(),
-				readable_timestamp: format_timestamp(
-					resource.data().timestamp,
-					&self.config.timestamp_format,
-				)?,
-			});
+		if !out_short.exists() {
+			std::fs::create_dir_all(&out_short)?;
+		}
+		if !out_long.exists() {
+			std::fs::create_dir_all(&out_long)?;
 		}
 
+		let resource_has_tags = data.iter().any(|r| !r.resource.data().tags.is_empty());
+
 		#[allow(clippy::too_many_arguments)]
 		fn build_list(
 			builder: &SiteBuilder,
 			config: &ResourceBuilderConfig,
+			key: &CacheKey,
 			list: Vec<&ResourceTemplateData>,
 			title: &str,
 			tag: Option<&str>,
+			series: Option<&str>,
 			out_path: &Path,
 			items_per_page: usize,
 			resource_has_tags: bool,
+			rss_url: Option<&str>,
+			feeds: &[AlternateLink],
+			language: Option<&str>,
+			translations: &[Translation],
 		) -> eyre::Result<()> {
 			if !out_path.exists() {
 				std::fs::create_dir_all(out_path)?;
@@ -325,30 +918,47 @@ impl ResourceBuilder {
 			let mut next;
 			for (page, iter) in list.chunks(items_per_page).enumerate() {
 				next = (page + 1 != page_max).then_some(page + 2);
-				let out = builder.build_page_raw(
-					PageMetadata {
-						template: Some(config.resource_list_template.clone()),
-						title: Some(title.to_owned()),
-						..Default::default()
-					},
-					"",
-					ResourceListTemplateData {
-						resources: iter.to_vec(),
-						has_tags: resource_has_tags,
-						tag,
-						rss_enabled: config.rss.is_some(),
-						page: page + 1,
-						page_max,
-						previous,
-						next,
-					},
-				)?;
+				let list_data = ResourceListTemplateData {
+					resources: iter.to_vec(),
+					has_tags: resource_has_tags,
+					tag,
+					series,
+					rss_enabled: config.rss.is_some(),
+					rss_url,
+					feeds,
+					page: page + 1,
+					page_max,
+					previous,
+					next,
+				};
+				let key = key
+					.clone()
+					.add("list")
+					.add(title)
+					.add(language.unwrap_or_default())
+					.add_serialized(&list_data)?
+					.add_serialized(&translations)?;
+				let render = || {
+					builder.build_page_raw(
+						PageMetadata {
+							template: Some(config.resource_list_template.clone()),
+							title: Some(title.to_owned()),
+							alternates: feeds.to_vec(),
+							language: language.map(str::to_owned),
+							translations: translations.to_vec(),
+							..Default::default()
+						},
+						"",
+						&list_data,
+					)
+				};
 				if page == 0 {
-					std::fs::write(out_path.join("index.html"), &out)?;
+					builder.write_output(&out_path.join("index.html"), key.clone(), render)?;
 				}
-				std::fs::write(
-					out_path.join((page + 1).to_string()).with_extension("html"),
-					out,
+				builder.write_output(
+					&out_path.join((page + 1).to_string()).with_extension("html"),
+					key,
+					render,
 				)?;
 				previous = Some(page + 1);
 			}
@@ -360,20 +970,30 @@ impl ResourceBuilder {
 		build_list(
 			builder,
 			&self.config,
-			data.iter().collect(),
+			key,
+			data.to_vec(),
 			&self.config.list_title,
 			None,
+			None,
 			&out_long,
 			self.config.resources_per_page,
 			resource_has_tags,
+			self.config
+				.rss
+				.as_ref()
+				.map(|_| format!("/{output_path_lists}/{}", crate::feed::RSS_FILENAME))
+				.as_deref(),
+			&self.feed_alternates(&prefix),
+			language,
+			translations,
 		)?;
 
 		if resource_has_tags {
 			// Build resource lists by tag
 			let mut tags: BTreeMap<String, Vec<&ResourceTemplateData>> = BTreeMap::new();
-			for resource in &data {
+			for resource in data {
 				for tag in resource.resource.data().tags.iter().cloned() {
-					tags.entry(tag).or_default().push(resource);
+					tags.entry(tag).or_default().push(*resource);
 				}
 			}
 
@@ -385,7 +1005,7 @@ impl ResourceBuilder {
 						let count = data.len();
 						(
 							Link::new(
-								format!("/{}/tag/{tag}/", self.config.output_path_resources),
+								format!("/{output_path_resources}/tag/{tag}/"),
 								format!("{tag} ({count})"),
 							),
 							count,
@@ -393,77 +1013,294 @@ impl ResourceBuilder {
 					})
 					.collect();
 				links.sort_by(|(_, a), (_, b)| b.cmp(a));
-				let links = links.into_iter().map(|(l, _)| l).collect();
-				let out = crate::link_list::render_basic_link_list(
-					builder,
-					&self.config.tag_list_template,
-					links,
-					&self.config.tag_list_title,
+				let links: Vec<_> = links.into_iter().map(|(l, _)| l).collect();
+				builder.write_output(
+					&out_short.join("tags.html"),
+					key.clone()
+						.add("tags")
+						.add(&self.config.tag_list_title)
+						.add(language.unwrap_or_default())
+						.add_serialized(&links)?,
+					|| {
+						crate::link_list::render_basic_link_list(
+							builder,
+							&self.config.tag_list_template,
+							links,
+							&self.config.tag_list_title,
+							language,
+						)
+					},
 				)?;
-				std::fs::write(out_short.join("tags.html"), out)?;
 			}
 
 			for (tag, data) in tags {
+				let tag_path = format!("{output_path_resources}/tag/{tag}");
+				let mut feeds = self.feed_alternates(&prefix);
+				let mut rss_url = None;
+				if let Some(rss) = &self.config.rss
+					&& rss.per_tag
+				{
+					let title = format!("{} tagged {tag}", rss.title);
+					crate::feed::build_feeds(
+						builder,
+						rss,
+						key,
+						FeedInfo {
+							title: &title,
+							description: &rss.description,
+							list_path: &tag_path,
+							feed_path: &tag_path,
+							language,
+						},
+						&data,
+					)?;
+					feeds.splice(0..0, crate::feed::feed_alternates(rss, &title, &tag_path));
+					rss_url = Some(format!("/{tag_path}/{}", crate::feed::RSS_FILENAME));
+				}
+
 				build_list(
 					builder,
 					&self.config,
+					key,
 					data,
 					&format!("{} tagged {tag}", self.config.resource_name_plural),
 					Some(tag.as_str()),
+					None,
 					&out_short.join("tag").join(&tag),
 					self.config.resources_per_page,
 					true,
+					rss_url.as_deref(),
+					&feeds,
+					language,
+					&[],
 				)?;
 			}
 		}
 
-		// Build RSS feed
-		if let Some(rss) = &self.config.rss {
-			let mut items = Vec::with_capacity(data.len());
-			for resource in data {
-				items.push(
-					ItemBuilder::default()
-						.title(Some(resource.resource.data().title.to_owned()))
-						.link(Some(
-							builder
-								.site
-								.config
-								.base_url
-								.join(&format!(
-									"{}/{}",
-									self.config.output_path_resources, resource.id
-								))?
-								.to_string(),
-						))
-						.description(resource.resource.data().desc.clone())
-						.pub_date(Some(resource.resource.data().timestamp.format(&Rfc2822)?))
-						.content(Some(builder.tera.render(
-							&rss.template,
-							&tera::Context::from_serialize(resource)?,
-						)?))
-						.build(),
-				)
+		// Build series index pages
+		let mut series: BTreeMap<&str, Vec<&ResourceTemplateData>> = BTreeMap::new();
+		for resource in data {
+			if let Some(resource_series) = &resource.series_info {
+				series
+					.entry(resource_series.name)
+					.or_default()
+					.push(*resource);
+			}
+		}
+		// series pages are built to their slugs, so two series can't share one
+		let mut slugs = HashMap::new();
+		for name in series.keys() {
+			let slug = util::slugify(name);
+			if slug.is_empty() {
+				eyre::bail!("series {name:?} has no letters or numbers to build its URL from");
+			}
+			if let Some(other) = slugs.insert(slug, *name) {
+				eyre::bail!(
+					"series {other:?} and {name:?} would both be built to {}",
+					self.series_url(builder, name, language)
+				);
 			}
+		}
+		if !series.is_empty() {
+			let links: Vec<_> = series
+				.iter()
+				.map(|(name, data)| {
+					Link::new(
+						self.series_url(builder, name, language),
+						format!("{name} ({})", data.len()),
+					)
+				})
+				.collect();
+			let title = self.series_list_title();
+			builder.write_output(
+				&out_short.join("series.html"),
+				key.clone()
+					.add("series")
+					.add(&title)
+					.add(language.unwrap_or_default())
+					.add_serialized(&links)?,
+				|| {
+					crate::link_list::render_basic_link_list(
+						builder,
+						&self.config.tag_list_template,
+						links,
+						&title,
+						language,
+					)
+				},
+			)?;
+		}
+		// series are listed in order on a single page
+		for (name, mut data) in series {
+			data.sort_by_key(|resource| resource.series_info.as_ref().map(|series| series.part));
+			build_list(
+				builder,
+				&self.config,
+				key,
+				data,
+				name,
+				None,
+				Some(name),
+				&out_short.join("series").join(util::slugify(name)),
+				usize::MAX,
+				resource_has_tags,
+				None,
+				&self.feed_alternates(&prefix),
+				language,
+				&[],
+			)?;
+		}
 
-			let channel = ChannelBuilder::default()
-				.title(rss.title.clone())
-				.link(
-					builder
-						.site
-						.config
-						.base_url
-						.join(&format!("{}/", self.config.output_path_lists))
-						.expect("Should never fail"),
-				)
-				.description(rss.description.clone())
-				.last_build_date(Some(OffsetDateTime::now_utc().format(&Rfc2822)?))
-				.items(items)
-				.build();
-			channel.validate().wrap_err("Failed to validate RSS feed")?;
-			let out = channel.to_string();
-			std::fs::write(out_long.join("rss.xml"), out)?;
+		if let Some(archive) = &self.config.archive {
+			self.build_archive(builder, archive, key, language, data)?;
+		}
+
+		// Build feeds
+		if let Some(rss) = &self.config.rss {
+			crate::feed::build_feeds(
+				builder,
+				rss,
+				key,
+				FeedInfo {
+					title: &rss.title,
+					description: &rss.description,
+					list_path: &output_path_lists,
+					feed_path: &output_path_lists,
+					language,
+				},
+				data,
+			)?;
 		}
 
 		Ok(())
 	}
+
+	/// Builds the date-based archive pages for the resources in a single language.
+	fn build_archive(
+		&self,
+		builder: &SiteBuilder,
+		archive: &ResourceArchiveConfig,
+		key: &CacheKey,
+		language: Option<&str>,
+		data: &[&ResourceTemplateData],
+	) -> eyre::Result<()> {
+		let prefix = builder.site.config.language_prefix(language);
+		let archive_path = format!("{prefix}{}/archive", self.config.output_path_resources);
+		let archive_url = format!("/{archive_path}");
+		let out_path = builder.build_path.join(&archive_path);
+
+		let mut years: BTreeMap<i32, BTreeMap<Month, Vec<&ResourceTemplateData>>> = BTreeMap::new();
+		for resource in data {
+			let timestamp = resource.resource.data().timestamp;
+			years
+				.entry(timestamp.year())
+				.or_default()
+				.entry(timestamp.month())
+				.or_default()
+				.push(*resource);
+		}
+
+		let build_page = |out_path: &Path, title: &str, archive_data: &ArchiveTemplateData| {
+			builder.write_output(
+				out_path,
+				key.clone()
+					.add("archive")
+					.add(title)
+					.add(language.unwrap_or_default())
+					.add_serialized(archive_data)?,
+				|| {
+					builder.build_page_raw(
+						PageMetadata {
+							template: Some(archive.template.clone()),
+							title: Some(title.to_owned()),
+							alternates: self.feed_alternates(&prefix),
+							language: language.map(str::to_owned),
+							..Default::default()
+						},
+						"",
+						archive_data,
+					)
+				},
+			)
+		};
+
+		let mut links = Vec::new();
+		// newest first, like the resources themselves
+		for (year, months) in years.iter().rev() {
+			let year_path = out_path.join(year.to_string());
+			let month_links = |links: &mut Vec<Link>| {
+				for (month, resources) in months.iter().rev() {
+					links.push(Link::new(
+						format!("{archive_url}/{year}/{:02}/", *month as u8),
+						format!("{month} {year} ({})", resources.len()),
+					));
+				}
+			};
+
+			let resources: Vec<_> = months
+				.values()
+				.rev()
+				.flat_map(|resources| resources.iter().copied())
+				.collect();
+			links.push(Link::new(
+				format!("{archive_url}/{year}/"),
+				format!("{year} ({})", resources.len()),
+			));
+			let mut year_months = Vec::new();
+			if archive.months {
+				month_links(&mut links);
+				month_links(&mut year_months);
+			}
+			build_page(
+				&year_path.join("index.html"),
+				&format!("{} from {year}", self.config.resource_name_plural),
+				&ArchiveTemplateData {
+					resources,
+					year: *year,
+					month: None,
+					month_name: None,
+					months: year_months,
+					archive_url: &archive_url,
+				},
+			)?;
+
+			if !archive.months {
+				continue;
+			}
+			for (month, resources) in months {
+				build_page(
+					&year_path
+						.join(format!("{:02}", *month as u8))
+						.join("index.html"),
+					&format!("{} from {month} {year}", self.config.resource_name_plural),
+					&ArchiveTemplateData {
+						resources: resources.clone(),
+						year: *year,
+						month: Some(*month as u8),
+						month_name: Some(month.to_string()),
+						months: Vec::new(),
+						archive_url: &archive_url,
+					},
+				)?;
+			}
+		}
+
+		builder.write_output(
+			&out_path.with_extension("html"),
+			key.clone()
+				.add("archive")
+				.add(&archive.title)
+				.add(language.unwrap_or_default())
+				.add_serialized(&links)?,
+			|| {
+				crate::link_list::render_basic_link_list(
+					builder,
+					&self.config.tag_list_template,
+					links,
+					&archive.title,
+					language,
+				)
+			},
+		)
+	}
 }
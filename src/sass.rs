@@ -0,0 +1,76 @@
+//! Module containing the config for building the site's Sass stylesheets.
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// Config for building the site's Sass stylesheets.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SassConfig {
+	/// Extra paths to search for imported stylesheets in, relative to the site.
+	#[serde(default)]
+	pub load_paths: Vec<PathBuf>,
+	/// The output style for built stylesheets. If not set, stylesheets are minified except when serving.
+	#[serde(default)]
+	pub style: Option<SassStyle>,
+}
+
+impl SassConfig {
+	/// Builds the options used to compile stylesheets.
+	pub(crate) fn options(&self, site_path: &Path) -> grass::Options<'static> {
+		let mut options = grass::Options::default();
+		for load_path in &self.load_paths {
+			options = options.load_path(site_path.join(load_path));
+		}
+		if let Some(style) = self.style {
+			options = options.style(match style {
+				SassStyle::Expanded => grass::OutputStyle::Expanded,
+				SassStyle::Compressed => grass::OutputStyle::Compressed,
+			});
+		}
+		options
+	}
+}
+
+/// Output style for built stylesheets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SassStyle {
+	/// Each selector and declaration is written on its own line.
+	Expanded,
+	/// As many characters as possible are removed.
+	Compressed,
+}
+
+/// A Sass stylesheet to build.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SassSheet {
+	/// A stylesheet built to the same path in the styles directory, with a `.css` extension.
+	Path(PathBuf),
+	/// A stylesheet built to a custom path in the styles directory.
+	Mapped {
+		/// The stylesheet's path, relative to the site's Sass directory.
+		path: PathBuf,
+		/// The path to build the stylesheet to, relative to the styles directory.
+		output: PathBuf,
+	},
+}
+
+impl SassSheet {
+	/// The stylesheet's path, relative to the site's Sass directory.
+	pub fn path(&self) -> &Path {
+		match self {
+			Self::Path(path) => path,
+			Self::Mapped { path, .. } => path,
+		}
+	}
+
+	/// The path the stylesheet is built to, relative to the styles directory.
+	pub fn output_path(&self) -> PathBuf {
+		match self {
+			Self::Path(path) => path.with_extension("css"),
+			Self::Mapped { output, .. } => output.clone(),
+		}
+	}
+}
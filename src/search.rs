@@ -0,0 +1,161 @@
+//! Module containing the client-side search index builder.
+
+use eyre::Context;
+use pulldown_cmark::{Event, Options, Parser, TagEnd};
+use serde::Serialize;
+
+use crate::{
+	PageMetadata,
+	builder::{SiteBuilder, WEBDOG_DEFAULT_PATH},
+	frontmatter::FrontMatter,
+	util,
+};
+
+/// Filename for the search index, stored in the webdog path.
+pub const SEARCH_INDEX_FILENAME: &str = "search-index.json";
+/// Filename for the search script, stored in the webdog path.
+pub const SEARCH_SCRIPT_FILENAME: &str = "search.js";
+
+/// A single searchable page or resource.
+#[derive(Debug, Serialize)]
+struct SearchEntry {
+	/// The entry's URL, relative to the site root.
+	url: String,
+	/// The entry's title.
+	title: String,
+	/// The entry's description, if any.
+	#[serde(skip_serializing_if = "Option::is_none")]
+	desc: Option<String>,
+	/// The entry's tags.
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	tags: Vec<String>,
+	/// The entry's content as plain text.
+	text: String,
+}
+
+/// Builds the site's search index and search script, if enabled in the site config.
+pub(crate) fn build_search_index(builder: &SiteBuilder) -> eyre::Result<()> {
+	if !builder.site.config.search_index {
+		return Ok(());
+	}
+
+	let webdog_path = builder.build_path.join(
+		builder
+			.site
+			.config
+			.webdog_path
+			.as_deref()
+			.unwrap_or(WEBDOG_DEFAULT_PATH),
+	);
+	let search_js = include_str!("./embedded/js/search.js");
+	builder.write_asset(
+		&webdog_path.join(SEARCH_SCRIPT_FILENAME),
+		builder.cache_key().add(search_js),
+		|| Ok(search_js),
+	)?;
+
+	let entries = collect_entries(builder)?;
+	builder.write_output(
+		&webdog_path.join(SEARCH_INDEX_FILENAME),
+		builder.cache_key().add("search").add_serialized(&entries)?,
+		|| Ok(serde_json::to_string(&entries)?),
+	)
+}
+
+/// Collects every searchable page and resource in the site.
+fn collect_entries(builder: &SiteBuilder) -> eyre::Result<Vec<SearchEntry>> {
+	let mut entries = Vec::new();
+
+	let mut page_names: Vec<_> = builder.site.page_index.keys().collect();
+	page_names.sort();
+	for page_name in page_names {
+		let page_path = &builder.site.page_index[page_name];
+		let input = std::fs::read_to_string(page_path)
+			.with_context(|| format!("Failed to read page at {}", page_path.display()))?;
+		let page = FrontMatter::<PageMetadata>::parse(input)?;
+		let data = page.data.unwrap_or_default();
+		if data.exclude_from_search {
+			continue;
+		}
+		entries.push(SearchEntry {
+			url: util::page_url(page_name),
+			title: data.title.unwrap_or_else(|| page_name.clone()),
+			desc: data.embed.and_then(|embed| embed.description),
+			tags: Vec::new(),
+			text: markdown_text(
+				&page.content,
+				builder
+					.site
+					.config
+					.markdown
+					.with_overrides(data.markdown.as_ref())?
+					.options(),
+			),
+		});
+	}
+
+	let mut prefixes: Vec<_> = builder.resource_builders.keys().collect();
+	prefixes.sort();
+	for prefix in prefixes {
+		let resource_builder = &builder.resource_builders[prefix];
+		for (id, resource) in &resource_builder.loaded_metadata {
+			let data = resource.data();
+			if data.draft {
+				continue;
+			}
+			entries.push(SearchEntry {
+				url: resource_builder.url(builder, id, data.language.as_deref()),
+				title: data.title.clone(),
+				desc: data.desc.clone(),
+				tags: data.tags.clone(),
+				// resource content is already rendered to HTML when loaded
+				text: html_text(&resource.content),
+			});
+		}
+	}
+
+	Ok(entries)
+}
+
+/// Strips the formatting from some Markdown, leaving only its text.
+fn markdown_text(input: &str, options: Options) -> String {
+	let mut text = String::new();
+	for event in Parser::new_ext(input, options) {
+		match event {
+			Event::Text(s) | Event::Code(s) | Event::InlineMath(s) | Event::DisplayMath(s) => {
+				text.push_str(&s)
+			}
+			Event::SoftBreak
+			| Event::HardBreak
+			| Event::End(
+				TagEnd::Paragraph
+				| TagEnd::Heading(_)
+				| TagEnd::Item
+				| TagEnd::CodeBlock
+				| TagEnd::TableCell,
+			) => text.push(' '),
+			_ => {}
+		}
+	}
+	text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Strips the tags from some HTML, leaving only its text.
+fn html_text(input: &str) -> String {
+	use kuchikiki::traits::*;
+
+	let document = kuchikiki::parse_html().one(input).document_node;
+	// code block copy buttons, heading anchors, and math source aren't part of the content
+	for el in document
+		.select(".wd-codeblock .copy, .wd-heading-anchor, math annotation")
+		.expect("selector should be valid")
+		.collect::<Vec<_>>()
+	{
+		el.as_node().detach();
+	}
+	document
+		.text_contents()
+		.split_whitespace()
+		.collect::<Vec<_>>()
+		.join(" ")
+}
@@ -10,6 +10,7 @@ use std::{
 use eyre::Context;
 use futures::SinkExt;
 use hotwatch::{EventKind, Hotwatch};
+use serde::Serialize;
 use warp::{
 	Filter,
 	hyper::StatusCode,
@@ -19,17 +20,91 @@ use warp::{
 };
 
 use crate::{
-	PAGES_PATH, RESOURCES_PATH, ROOT_PATH, SASS_PATH, Site, SiteBuilder, SiteConfig, TEMPLATES_PATH,
+	PAGES_PATH, RESOURCES_PATH, ROOT_PATH, SASS_PATH, SYNTAXES_PATH, Site, SiteBuilder, SiteConfig,
+	TEMPLATES_PATH, THEMES_PATH, dependencies::Dependent, util,
 };
 
-/// Helper to get the "name" of a path.
-fn get_name(path: &Path) -> (PathBuf, String) {
-	let name = path.with_extension("");
-	let name_str = name
-		.display()
-		.to_string()
-		.replace(std::path::MAIN_SEPARATOR, "/");
-	(name, name_str)
+/// A message sent to the pages connected to the dev server.
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum DevMessage {
+	/// The site was rebuilt, so pages should reload.
+	Reload,
+	/// The site failed to build, so pages should show the error.
+	Error(BuildError),
+}
+
+/// An error which occurred while building the site, shown in the dev server's error overlay.
+#[derive(Debug, Clone, Serialize)]
+struct BuildError {
+	/// The file the error occurred in, relative to the site, if known.
+	file: Option<String>,
+	/// The line the error occurred on, if known.
+	line: Option<usize>,
+	/// The error message, including its causes.
+	message: String,
+}
+
+impl BuildError {
+	/// Creates a build error from an error report, along with the file which was being built.
+	fn new(report: &eyre::Report, site_path: &Path, file: Option<&Path>) -> Self {
+		let message = report
+			.chain()
+			.map(|e| e.to_string())
+			.collect::<Vec<_>>()
+			.join("\n");
+		let mut file = file.map(util::template_name);
+		let mut line = None;
+		// Sass errors end with the location they occurred at, i.e. `sass/index.scss:12:3`
+		if let Some((location_file, location_line)) = message.lines().rev().find_map(parse_location)
+		{
+			let location_file = Path::new(location_file);
+			file = Some(util::template_name(
+				location_file
+					.strip_prefix(site_path)
+					.unwrap_or(location_file),
+			));
+			line = Some(location_line);
+		}
+		Self {
+			file,
+			line,
+			message,
+		}
+	}
+}
+
+/// Parses a `file:line:column` location from a line of an error message.
+fn parse_location(line: &str) -> Option<(&str, usize)> {
+	let mut parts = line.trim().rsplitn(3, ':');
+	parts.next()?.parse::<usize>().ok()?;
+	let line = parts.next()?.parse().ok()?;
+	let file = parts.next()?.trim_start_matches("./");
+	(!file.is_empty()).then_some((file, line))
+}
+
+/// Sends a message to every page connected to the dev server.
+fn broadcast(peers: &Mutex<HashMap<SocketAddr, WebSocket>>, message: &DevMessage) {
+	let text = serde_json::to_string(message).expect("dev messages should always serialize");
+	let mut peers = peers.lock().unwrap();
+	let mut to_remove = Vec::new();
+	for (addr, peer) in peers.iter_mut() {
+		let task = async {
+			peer.send(Message::text(text.clone())).await?;
+			Ok::<_, eyre::Error>(())
+		};
+		let result = futures::executor::block_on(task);
+		if let Err(e) = &result {
+			eprintln!("{}", e);
+		}
+		// reloading pages reconnect on their own
+		if result.is_err() || matches!(message, DevMessage::Reload) {
+			to_remove.push(*addr);
+		}
+	}
+	for addr in &to_remove {
+		peers.remove(addr);
+	}
 }
 
 fn with_build_path(
@@ -45,25 +120,20 @@ fn rel(path: &Path, prefix: &Path) -> Result<PathBuf, std::path::StripPrefixErro
 
 /// Helper to build resources in the case of creation or removal.
 fn build_resources(builder: &mut SiteBuilder, path: &Path) -> eyre::Result<()> {
-	let paths: Vec<_> = builder
+	// resource types may have overlapping source paths, so every matching type needs updating
+	let mut prefixes: Vec<_> = builder
 		.resource_builders
-		.values()
-		.map(|b| {
-			(
-				b.config.source_path.clone(),
-				path.strip_prefix(&b.config.source_path),
-			)
-		})
-		.filter_map(|(p, v)| v.ok().map(|v| (p, v)))
+		.iter()
+		.filter(|(_, b)| path.strip_prefix(&b.config.source_path).is_ok())
+		.map(|(prefix, _)| prefix.clone())
 		.collect();
-	if paths.len() > 1 {
-		todo!("handle more than one possible match");
-	}
-	if let Some((prefix, _path)) = paths.first() {
-		// HACK: this could get very inefficient with a larger number of resources. should definitely optimize
-		builder.reload_resource_builder(prefix)?;
-		builder.build_resources(prefix)?;
+	prefixes.sort();
+	let full_path = builder.site.site_path.join(RESOURCES_PATH).join(path);
+	for prefix in prefixes {
+		builder.reload_resource(&prefix, &full_path)?;
+		builder.build_resources(&prefix)?;
 	}
+	crate::search::build_search_index(builder)?;
 	Ok(())
 }
 
@@ -78,35 +148,48 @@ fn create(
 		return Ok(());
 	}
 	println!("{relative_path:?}");
-	if let Ok(page_path) = relative_path.strip_prefix(PAGES_PATH) {
-		let (_page_name, page_name_str) = get_name(page_path);
-
-		builder
-			.site
-			.page_index
-			.insert(page_name_str.clone(), path.to_owned());
+	if relative_path.starts_with(PAGES_PATH) {
+		let page_name = builder.site.index_page(path)?;
 		if build {
-			builder.build_page(&page_name_str)?;
+			builder.build_page(&page_name)?;
+			// the page's translations link to it
+			for (translation, _) in builder.site.page_translations(&page_name) {
+				if translation != page_name {
+					builder.build_page(&translation)?;
+				}
+			}
+			crate::search::build_search_index(builder)?;
 		}
-	} else if let Ok(_template_path) = relative_path.strip_prefix(TEMPLATES_PATH) {
-		builder.tera.full_reload()?;
+	} else if let Ok(template_path) = relative_path.strip_prefix(TEMPLATES_PATH) {
+		builder.reload_templates()?;
 		if build {
-			builder.build_all_pages()?;
-			builder.build_all_resources()?;
+			builder.build_template_dependents(&util::template_name(template_path))?;
 		}
 	} else if relative_path.display().to_string() == SiteConfig::FILENAME {
 		let new_config = serde_yaml_ng::from_str(&std::fs::read_to_string(path)?)?;
 		builder.site.config = new_config;
+		builder.site.reindex_pages()?;
+		builder.reload()?;
+		builder.build_all()?;
+	} else if relative_path.starts_with(SYNTAXES_PATH) || relative_path.starts_with(THEMES_PATH) {
+		builder.reload_highlighting()?;
 		builder.reload()?;
 		builder.build_all()?;
-	} else if let Ok(_sass_path) = relative_path.strip_prefix(SASS_PATH) {
+	} else if is_sass_path(builder, relative_path) {
 		if build {
 			builder.build_sass().wrap_err("Failed to rebuild Sass")?;
 		}
 	} else if let Ok(root_path) = relative_path.strip_prefix(ROOT_PATH) {
 		std::fs::copy(path, builder.build_path.join(root_path))?;
+		if build && crate::images::is_image(path) {
+			builder.reload_images()?;
+			builder.build_all()?;
+		}
 	} else if let Ok(resources_path) = relative_path.strip_prefix(RESOURCES_PATH) {
 		build_resources(builder, resources_path)?;
+	} else if is_cdn_mirror_image(builder, relative_path) && build {
+		builder.reload_images()?;
+		builder.build_all()?;
 	}
 
 	Ok(())
@@ -117,28 +200,79 @@ fn remove(builder: &mut SiteBuilder, path: &Path, relative_path: &Path) -> eyre:
 	if path.is_dir() {
 		return Ok(());
 	}
-	if let Ok(page_path) = relative_path.strip_prefix(PAGES_PATH) {
-		let (page_name, page_name_str) = get_name(page_path);
-
-		builder.site.page_index.remove(&page_name_str);
-		std::fs::remove_file(builder.build_path.join(page_name.with_extension("html")))
+	if relative_path.starts_with(PAGES_PATH) {
+		let translations = builder
+			.site
+			.page_name(path)
+			.map(|page_name| builder.site.page_translations(&page_name))
+			.unwrap_or_default();
+		let Some(page_name) = builder.site.unindex_page(path) else {
+			return Ok(());
+		};
+		builder
+			.dependencies
+			.lock()
+			.expect("dependency graph lock poisoned")
+			.remove(&Dependent::Page(page_name.clone()));
+		std::fs::remove_file(builder.build_path.join(&page_name).with_extension("html"))
 			.with_context(|| format!("Failed to remove page at {:?}", path))?;
-	} else if let Ok(_template_path) = relative_path.strip_prefix(TEMPLATES_PATH) {
-		builder.tera.full_reload()?;
+		// the page's translations no longer link to it
+		for (translation, _) in translations {
+			if builder.site.page_index.contains_key(&translation) {
+				builder.build_page(&translation)?;
+			}
+		}
+		crate::search::build_search_index(builder)?;
+	} else if let Ok(template_path) = relative_path.strip_prefix(TEMPLATES_PATH) {
+		builder.reload_templates()?;
 		builder
-			.build_all_pages()
-			.wrap_err("Failed to rebuild pages")?;
-	} else if let Ok(_sass_path) = relative_path.strip_prefix(SASS_PATH) {
+			.build_template_dependents(&util::template_name(template_path))
+			.wrap_err("Failed to rebuild template dependents")?;
+	} else if relative_path.starts_with(SYNTAXES_PATH) || relative_path.starts_with(THEMES_PATH) {
+		builder.reload_highlighting()?;
+		builder.reload()?;
+		builder.build_all()?;
+	} else if is_sass_path(builder, relative_path) {
 		builder.build_sass().wrap_err("Failed to rebuild Sass")?;
 	} else if let Ok(root_path) = relative_path.strip_prefix(ROOT_PATH) {
 		std::fs::remove_file(builder.build_path.join(root_path))?;
+		if crate::images::is_image(path) {
+			builder.reload_images()?;
+			builder.build_all()?;
+		}
 	} else if let Ok(resources_path) = relative_path.strip_prefix(RESOURCES_PATH) {
 		build_resources(builder, resources_path)?;
+	} else if is_cdn_mirror_image(builder, relative_path) {
+		builder.reload_images()?;
+		builder.build_all()?;
 	}
 
 	Ok(())
 }
 
+/// Whether the given path is in the site's Sass directory or one of its Sass load paths.
+fn is_sass_path(builder: &SiteBuilder, relative_path: &Path) -> bool {
+	relative_path.starts_with(SASS_PATH)
+		|| builder
+			.site
+			.config
+			.sass
+			.load_paths
+			.iter()
+			.any(|path| relative_path.starts_with(path))
+}
+
+/// Whether the given path is an image in the site's local copy of the CDN.
+fn is_cdn_mirror_image(builder: &SiteBuilder, relative_path: &Path) -> bool {
+	builder
+		.site
+		.config
+		.cdn_mirror
+		.as_ref()
+		.is_some_and(|mirror| relative_path.starts_with(mirror))
+		&& crate::images::is_image(relative_path)
+}
+
 /// Decides whether to skip a path in the watcher.
 fn skip_path(builder: &SiteBuilder, path: &Path) -> bool {
 	path.strip_prefix(&builder.build_path).is_ok()
@@ -152,17 +286,28 @@ impl Site {
 		let mut builder = SiteBuilder::new(self, true)?.prepare()?;
 		let site = &builder.site;
 		let build_path = builder.build_path.clone();
+		// The most recent build error, shown to pages until the next successful build
+		let build_error: Arc<Mutex<Option<BuildError>>> = Default::default();
 
 		// Perform initial build
-		for page_name in site.page_index.keys() {
+		for (page_name, page_path) in &site.page_index {
 			if let Err(e) = builder.build_page(page_name) {
 				eprintln!("Failed to build page {}: {}", page_name, e);
+				*build_error.lock().unwrap() = Some(BuildError::new(
+					&e,
+					&site.site_path,
+					page_path.strip_prefix(&site.site_path).ok(),
+				));
 			}
 		}
-		builder.build_sass().wrap_err("Failed to build Sass")?;
+		if let Err(e) = builder.build_sass() {
+			eprintln!("Failed to build Sass: {e:?}");
+			*build_error.lock().unwrap() = Some(BuildError::new(&e, &site.site_path, None));
+		}
 		builder
 			.build_all_resources()
 			.wrap_err("Failed to build resources")?;
+		crate::search::build_search_index(&builder).wrap_err("Failed to build search index")?;
 
 		// Map of websocket connections
 		let peers: Arc<Mutex<HashMap<SocketAddr, WebSocket>>> =
@@ -171,6 +316,7 @@ impl Site {
 		// Watch for changes to the site
 		let mut hotwatch = Hotwatch::new().expect("Hotwatch failed to initialize");
 		let hw_peers = peers.clone();
+		let hw_build_error = build_error.clone();
 		hotwatch
 			.watch(site.site_path.clone(), move |event| {
 				let peers = hw_peers.clone();
@@ -226,25 +372,24 @@ impl Site {
 				match r {
 					Ok(reload) => {
 						if reload {
-							// Send reload event to connected websockets
-							let mut peers = peers.lock().unwrap();
-							let mut to_remove = Vec::new();
-							for (addr, peer) in peers.iter_mut() {
-								let task = async {
-									peer.send(Message::text("reload".to_string())).await?;
-									Ok::<_, eyre::Error>(())
-								};
-								to_remove.push(*addr);
-								if let Err(e) = futures::executor::block_on(task) {
-									eprintln!("{}", e);
-								}
-							}
-							for addr in &to_remove {
-								peers.remove(addr);
-							}
+							*hw_build_error.lock().unwrap() = None;
+							broadcast(&peers, &DevMessage::Reload);
 						}
 					}
-					Err(e) => eprintln!("Failed to update: {:?}", e),
+					Err(e) => {
+						eprintln!("Failed to update: {:?}", e);
+						let site_path = &builder.site.site_path;
+						let error = BuildError::new(
+							&e,
+							site_path,
+							event
+								.paths
+								.last()
+								.and_then(|path| path.strip_prefix(site_path).ok()),
+						);
+						*hw_build_error.lock().unwrap() = Some(error.clone());
+						broadcast(&peers, &DevMessage::Error(error));
+					}
 				}
 			})
 			.expect("Failed to watch file");
@@ -254,11 +399,22 @@ impl Site {
 			.and(warp::filters::addr::remote())
 			.and_then(move |ws: warp::ws::Ws, addr| {
 				let peers = peers.clone();
+				let build_error = build_error.clone();
 				async move {
 					// Add websocket connection to peers list
 					if let Some(addr) = addr {
 						let peers = peers.clone();
-						return Ok(ws.on_upgrade(move |websocket| async move {
+						return Ok(ws.on_upgrade(move |mut websocket| async move {
+							// Show the current build error to newly loaded pages
+							let error = build_error.lock().unwrap().clone();
+							if let Some(error) = error {
+								let text = serde_json::to_string(&DevMessage::Error(error))
+									.expect("dev messages should always serialize");
+								if let Err(e) = websocket.send(Message::text(text)).await {
+									eprintln!("{}", e);
+									return;
+								}
+							}
 							peers.lock().unwrap().insert(addr, websocket);
 						}));
 					}
@@ -282,14 +438,7 @@ impl Site {
 							return Ok(res);
 						}
 
-						let mut p = build_path.join(p.as_ref());
-
-						if !p.exists() {
-							p = p.with_extension("html");
-						}
-						if p.is_dir() {
-							p = p.join("index.html");
-						}
+						let p = util::resolve_build_path(&build_path, p.as_ref());
 
 						if p.exists() {
 							let mut res = Response::new("".into());
@@ -0,0 +1,209 @@
+//! Module containing the sitemap builder.
+
+use std::collections::BTreeMap;
+
+use eyre::Context;
+use time::{OffsetDateTime, UtcOffset, format_description::well_known::Rfc3339};
+
+use crate::{PageMetadata, builder::SiteBuilder, frontmatter::FrontMatter, util};
+
+/// Filename for the site's sitemap, or its sitemap index if it's split.
+pub const SITEMAP_FILENAME: &str = "sitemap.xml";
+/// The maximum number of URLs allowed in a single sitemap.
+const MAX_SITEMAP_URLS: usize = 50_000;
+
+/// Builds the site's sitemap, splitting it into multiple sitemaps with an index if needed.
+pub(crate) fn build_sitemap(builder: &SiteBuilder) -> eyre::Result<()> {
+	let entries = collect_entries(builder)?;
+	let base_url = &builder.site.config.base_url;
+	let key = builder
+		.cache_key()
+		.add("sitemap")
+		.add_serialized(&entries)?;
+
+	if entries.len() <= MAX_SITEMAP_URLS {
+		let entries: Vec<_> = entries.iter().collect();
+		return builder.write_output(&builder.build_path.join(SITEMAP_FILENAME), key, || {
+			Ok(urlset(&entries))
+		});
+	}
+
+	let entries: Vec<_> = entries.iter().collect();
+	let mut sitemaps = Vec::new();
+	for (i, chunk) in entries.chunks(MAX_SITEMAP_URLS).enumerate() {
+		let filename = format!("sitemap-{}.xml", i + 1);
+		builder.write_output(
+			&builder.build_path.join(&filename),
+			key.clone().add(&filename),
+			|| Ok(urlset(chunk)),
+		)?;
+		let lastmod = chunk
+			.iter()
+			.filter_map(|(_, lastmod)| lastmod.as_ref())
+			.max();
+		sitemaps.push((base_url.join(&filename)?.to_string(), lastmod));
+	}
+
+	builder.write_output(&builder.build_path.join(SITEMAP_FILENAME), key, || {
+		let mut s = String::from(
+			r#"<?xml version="1.0" encoding="UTF-8"?><sitemapindex xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+		);
+		for (loc, lastmod) in &sitemaps {
+			s.push_str(&entry("sitemap", loc, lastmod.map(|l| l.as_str())));
+		}
+		s.push_str("</sitemapindex>");
+		Ok(s)
+	})
+}
+
+/// When each of a resource type's lists in a single language was last modified.
+#[derive(Default)]
+struct ResourceLists<'a> {
+	/// The newest resource's timestamp, if any.
+	newest: Option<OffsetDateTime>,
+	/// The newest resource's timestamp for each tag.
+	tags: BTreeMap<&'a str, OffsetDateTime>,
+	/// The newest resource's timestamp for each series.
+	series: BTreeMap<&'a str, OffsetDateTime>,
+	/// The newest resource's timestamp for each year and month, for archive pages.
+	months: BTreeMap<(i32, u8), OffsetDateTime>,
+}
+
+/// Collects every URL in the site along with when it was last modified, if known.
+fn collect_entries(builder: &SiteBuilder) -> eyre::Result<BTreeMap<String, Option<String>>> {
+	let base_url = &builder.site.config.base_url;
+	let mut entries = BTreeMap::new();
+	let mut add = |path: &str, lastmod: Option<OffsetDateTime>| -> eyre::Result<()> {
+		let lastmod = lastmod
+			.map(|lastmod| lastmod.to_offset(UtcOffset::UTC).format(&Rfc3339))
+			.transpose()?;
+		entries.insert(base_url.join(path)?.to_string(), lastmod);
+		Ok(())
+	};
+
+	for (page_name, page_path) in &builder.site.page_index {
+		let input = std::fs::read_to_string(page_path)
+			.with_context(|| format!("Failed to read page at {}", page_path.display()))?;
+		let page = FrontMatter::<PageMetadata>::parse(input)?;
+		if page.data.is_some_and(|data| data.exclude_from_sitemap) {
+			continue;
+		}
+		let lastmod = std::fs::metadata(page_path)?.modified()?.into();
+		add(
+			util::page_url(page_name).trim_start_matches('/'),
+			Some(lastmod),
+		)?;
+	}
+
+	let mut prefixes: Vec<_> = builder.resource_builders.keys().collect();
+	prefixes.sort();
+	for prefix in prefixes {
+		let resource_builder = &builder.resource_builders[prefix];
+		let config = &resource_builder.config;
+		// each language has its own lists, with the default language's always being built
+		let mut languages: BTreeMap<String, ResourceLists> = BTreeMap::new();
+		languages.insert(
+			builder.site.config.language_prefix(
+				builder
+					.site
+					.config
+					.i18n
+					.as_ref()
+					.map(|i18n| i18n.default_language.as_str()),
+			),
+			Default::default(),
+		);
+		for (id, resource) in &resource_builder.loaded_metadata {
+			let data = resource.data();
+			if data.draft {
+				continue;
+			}
+			let language_prefix = builder
+				.site
+				.config
+				.language_prefix(data.language.as_deref());
+			add(
+				&format!("{language_prefix}{}/{id}", config.output_path_resources),
+				Some(data.timestamp),
+			)?;
+			let lists = languages.entry(language_prefix).or_default();
+			lists.newest = lists.newest.max(Some(data.timestamp));
+			for tag in &data.tags {
+				let lastmod = lists.tags.entry(tag).or_insert(data.timestamp);
+				*lastmod = (*lastmod).max(data.timestamp);
+			}
+			if let Some(series) = &data.series {
+				let lastmod = lists.series.entry(series).or_insert(data.timestamp);
+				*lastmod = (*lastmod).max(data.timestamp);
+			}
+			let lastmod = lists
+				.months
+				.entry((data.timestamp.year(), data.timestamp.month() as u8))
+				.or_insert(data.timestamp);
+			*lastmod = (*lastmod).max(data.timestamp);
+		}
+		for (language_prefix, lists) in languages {
+			let resources_path = format!("{language_prefix}{}", config.output_path_resources);
+			add(
+				&format!("{language_prefix}{}/", config.output_path_lists),
+				lists.newest,
+			)?;
+			if !lists.tags.is_empty() {
+				add(&format!("{resources_path}/tags"), lists.newest)?;
+			}
+			for (tag, lastmod) in lists.tags {
+				add(&format!("{resources_path}/tag/{tag}/"), Some(lastmod))?;
+			}
+			if !lists.series.is_empty() {
+				add(&format!("{resources_path}/series"), lists.newest)?;
+			}
+			for (series, lastmod) in lists.series {
+				add(
+					&format!("{resources_path}/series/{}/", util::slugify(series)),
+					Some(lastmod),
+				)?;
+			}
+			if let Some(archive) = &config.archive {
+				add(&format!("{resources_path}/archive"), lists.newest)?;
+				let mut years: BTreeMap<i32, OffsetDateTime> = BTreeMap::new();
+				for ((year, month), lastmod) in lists.months {
+					let year_lastmod = years.entry(year).or_insert(lastmod);
+					*year_lastmod = (*year_lastmod).max(lastmod);
+					if archive.months {
+						add(
+							&format!("{resources_path}/archive/{year}/{month:02}/"),
+							Some(lastmod),
+						)?;
+					}
+				}
+				for (year, lastmod) in years {
+					add(&format!("{resources_path}/archive/{year}/"), Some(lastmod))?;
+				}
+			}
+		}
+	}
+
+	Ok(entries)
+}
+
+/// Renders a sitemap for the given entries.
+fn urlset(entries: &[(&String, &Option<String>)]) -> String {
+	let mut s = String::from(
+		r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#,
+	);
+	for (loc, lastmod) in entries {
+		s.push_str(&entry("url", loc, lastmod.as_deref()));
+	}
+	s.push_str("</urlset>");
+	s
+}
+
+/// Renders a single sitemap entry with the given tag name.
+fn entry(tag: &str, loc: &str, lastmod: Option<&str>) -> String {
+	let mut s = format!("<{tag}><loc>{}</loc>", util::escape_attribute(loc));
+	if let Some(lastmod) = lastmod {
+		s.push_str(&format!("<lastmod>{lastmod}</lastmod>"));
+	}
+	s.push_str(&format!("</{tag}>"));
+	s
+}
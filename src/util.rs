@@ -1,17 +1,29 @@
 //! Module containing various utilities.
 
-use std::path::Path;
+use std::{
+	collections::HashMap,
+	path::{Path, PathBuf},
+};
 
-use pulldown_cmark::{Options, Parser};
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use serde::{Deserialize, Serialize};
 use time::OffsetDateTime;
 
-use crate::builder::SiteBuilder;
+use crate::{TocEntry, builder::SiteBuilder, cache::CacheKey};
 
 /// Simple helper to remove the contents of a directory without removing the directory itself.
 pub fn remove_dir_contents(path: &Path) -> eyre::Result<()> {
+	remove_dir_contents_except(path, None)
+}
+
+/// Removes the contents of a directory without removing the directory itself, keeping the given entry if any.
+pub fn remove_dir_contents_except(path: &Path, except: Option<&Path>) -> eyre::Result<()> {
 	for entry in path.read_dir()? {
 		let entry = entry?;
 		let path = entry.path();
+		if Some(path.as_path()) == except {
+			continue;
+		}
 		if path.is_file() {
 			std::fs::remove_file(&path)?;
 		} else {
@@ -22,58 +34,197 @@ pub fn remove_dir_contents(path: &Path) -> eyre::Result<()> {
 	Ok(())
 }
 
+/// Gets a template's name from its path relative to the templates directory.
+pub fn template_name(path: &Path) -> String {
+	path.to_string_lossy()
+		.replace(std::path::MAIN_SEPARATOR, "/")
+}
+
+/// Resolves a URL path to the file it would be served from in the build directory, trying `.html` and `index.html` files.
+pub fn resolve_build_path(build_path: &Path, url_path: &str) -> PathBuf {
+	let mut p = build_path.join(url_path);
+	if !p.exists() {
+		p = p.with_extension("html");
+	}
+	if p.is_dir() {
+		p = p.join("index.html");
+	}
+	p
+}
+
+/// Gets the URL a page is served at from its name, i.e. `blog/index` is served at `/blog/`.
+pub fn page_url(page_name: &str) -> String {
+	if page_name == "index" {
+		"/".to_string()
+	} else if let Some(dir) = page_name.strip_suffix("/index") {
+		format!("/{dir}/")
+	} else {
+		format!("/{page_name}")
+	}
+}
+
+/// Escapes a string for use inside of an HTML attribute or XML text.
+pub fn escape_attribute(input: &str) -> String {
+	input
+		.replace('&', "&amp;")
+		.replace('"', "&quot;")
+		.replace('\'', "&#39;")
+		.replace('<', "&lt;")
+		.replace('>', "&gt;")
+}
+
 /// Helper to format a timestamp according to the given format.
 pub fn format_timestamp(ts: OffsetDateTime, format: &str) -> eyre::Result<String> {
 	let fmt = time::format_description::parse_borrowed::<2>(format)?;
 	Ok(ts.format(&fmt)?)
 }
 
+/// Rendered markdown along with its table of contents.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RenderedMarkdown {
+	/// The rendered HTML.
+	pub html: String,
+	/// The table of contents built from the markdown's headings.
+	pub toc: Vec<TocEntry>,
+}
+
+/// Helper to convert some text to a slug for use in IDs and URLs.
+pub fn slugify(input: &str) -> String {
+	let mut slug = String::new();
+	for c in input.trim().chars() {
+		if c.is_alphanumeric() {
+			slug.extend(c.to_lowercase());
+		} else if (c.is_whitespace() || c == '-' || c == '_') && !slug.ends_with('-') {
+			slug.push('-');
+		}
+	}
+	slug.trim_matches('-').to_string()
+}
+
+/// Helper to render markdown, reusing the output of a previous build if the markdown and everything it's rendered with are unchanged.
+pub fn render_markdown_cached(
+	builder: &SiteBuilder,
+	input: &str,
+	overrides: Option<&serde_yaml_ng::Mapping>,
+) -> eyre::Result<RenderedMarkdown> {
+	let key = CacheKey::new()
+		.add(builder.markdown_base())
+		.add(input)
+		.add_serialized(&overrides)?
+		.finish();
+	if let Some(rendered) = builder.cache.rendered_markdown(&key) {
+		return Ok(rendered);
+	}
+	let rendered = render_markdown(builder, input, overrides)?;
+	builder.cache.record_markdown(&key, &rendered);
+	Ok(rendered)
+}
+
 /// Helper to render markdown.
-pub fn render_markdown(builder: &SiteBuilder, input: &str) -> eyre::Result<String> {
-	let mut language = None;
-	let parser = Parser::new_ext(input, Options::all()).filter_map(|event| {
-		// syntax highlighting for code blocks
+///
+/// The site's Markdown extensions are used, with the given overrides from the page applied.
+pub fn render_markdown(
+	builder: &SiteBuilder,
+	input: &str,
+	overrides: Option<&serde_yaml_ng::Mapping>,
+) -> eyre::Result<RenderedMarkdown> {
+	let options = builder
+		.site
+		.config
+		.markdown
+		.with_overrides(overrides)?
+		.options();
+
+	// syntax highlighting for code blocks and math rendering
+	let mut parsed = Vec::new();
+	let mut code_block: Option<(pulldown_cmark::CowStr, String)> = None;
+	for event in Parser::new_ext(input, options) {
 		match event {
-			pulldown_cmark::Event::Start(pulldown_cmark::Tag::CodeBlock(
-				pulldown_cmark::CodeBlockKind::Fenced(name),
-			)) => {
-				language = Some(name);
-				None
+			Event::Start(Tag::CodeBlock(pulldown_cmark::CodeBlockKind::Fenced(info))) => {
+				code_block = Some((info, String::new()));
+			}
+			Event::Text(text) if code_block.is_some() => {
+				code_block
+					.as_mut()
+					.expect("checked above")
+					.1
+					.push_str(&text);
+			}
+			Event::End(TagEnd::CodeBlock) if code_block.is_some() => {
+				let (info, code) = code_block.take().expect("checked above");
+				parsed.push(Event::Html(
+					crate::code::render_code_block(builder, &code, &info)?.into(),
+				));
+			}
+			Event::InlineMath(tex) => {
+				parsed.push(Event::InlineHtml(
+					crate::math::render_math(&tex, false).into(),
+				));
 			}
-			pulldown_cmark::Event::Text(code) => {
-				if let Some(language) = language.take() {
-					let syntax_reference = builder
-						.syntax_set
-						.find_syntax_by_token(&language)
-						.unwrap_or_else(|| builder.syntax_set.find_syntax_plain_text());
-					let html = format!(
-						r#"<div class="wd-codeblock">
-							<button class="copy">Copy</button>
-							{}
-						</div>"#,
-						syntect::html::highlighted_html_for_string(
-							&code,
-							&builder.syntax_set,
-							syntax_reference,
-							builder
-								.theme_set
-								.themes
-								.get(&builder.site.config.code_theme)
-								.as_ref()
-								.expect("should never fail"),
-						)
-						.expect("failed to highlight syntax")
-					);
-					Some(pulldown_cmark::Event::Html(html.into()))
-				} else {
-					Some(pulldown_cmark::Event::Text(code))
+			Event::DisplayMath(tex) => {
+				parsed.push(Event::InlineHtml(
+					crate::math::render_math(&tex, true).into(),
+				));
+			}
+			_ => parsed.push(event),
+		}
+	}
+
+	// give headings stable IDs and collect them for the table of contents
+	let mut events = Vec::new();
+	let mut headings = Vec::new();
+	let mut used_ids: HashMap<String, usize> = HashMap::new();
+	let mut heading: Option<(usize, String)> = None;
+	for event in parsed {
+		match &event {
+			Event::Start(Tag::Heading { .. }) => {
+				heading = Some((events.len(), String::new()));
+			}
+			Event::Text(text) | Event::Code(text) => {
+				if let Some((_, title)) = &mut heading {
+					title.push_str(text);
 				}
 			}
-			_ => Some(event),
+			Event::End(TagEnd::Heading(level)) => {
+				if let Some((start, title)) = heading.take()
+					&& let Event::Start(Tag::Heading { id, .. }) = &mut events[start]
+				{
+					let base = match id {
+						Some(id) => id.to_string(),
+						None => Some(slugify(&title))
+							.filter(|slug| !slug.is_empty())
+							.unwrap_or_else(|| "section".to_string()),
+					};
+					let count = used_ids.entry(base.clone()).or_default();
+					let slug = if *count == 0 {
+						base
+					} else {
+						format!("{base}-{count}")
+					};
+					*count += 1;
+					*id = Some(slug.clone().into());
+					if builder.site.config.heading_anchors {
+						events.push(Event::Html(
+							format!(
+								r##"<a class="wd-heading-anchor" href="#{}" aria-label="link to this section">#</a>"##,
+								escape_attribute(&slug)
+							)
+							.into(),
+						));
+					}
+					headings.push((*level as usize, slug, title));
+				}
+			}
+			_ => {}
 		}
-	});
+		events.push(event);
+	}
+
 	let mut page_html = String::new();
-	pulldown_cmark::html::push_html(&mut page_html, parser);
+	pulldown_cmark::html::push_html(&mut page_html, events.into_iter());
 
-	Ok(page_html)
+	Ok(RenderedMarkdown {
+		html: page_html,
+		toc: TocEntry::tree(headings),
+	})
 }